@@ -0,0 +1,203 @@
+// A record of every donation (including its optional memo), kept per recipient.
+
+use crate::generic::{hash_account_id, storage_cost};
+use crate::{Amount, Contract, ContractExt, RecipientAccountId, StorageKey};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{near_bindgen, AccountId};
+
+pub const MAX_MEMO_LENGTH: usize = 256; // in bytes
+const DEFAULT_DONATIONS_LIMIT: u64 = 50;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DonationRecord {
+    pub donor: AccountId, // Kept even for anonymous donations so that failed donations can be refunded, but never shown in views.
+    pub anonymous: bool,
+    pub amount: Amount,
+    pub matched_amount: Amount,
+    pub fee: Amount, // The platform fee deducted from what the recipient received.
+    pub tip: Amount, // Went to this contract's reserve (not to the recipient).
+    pub employer_match: Option<(AccountId, Amount)>, // The donor's employer and how much it matched (see employers.rs). Also included in `matched_amount`.
+    pub memo: Option<String>,
+    pub timestamp_ms: u64,
+    pub refunded: bool,
+    pub storage_cost: Amount, // Deducted from the donation to pay for storing it (see send_donation). Not refunded if the transfer fails, since the record stays.
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationView {
+    pub donor: Option<AccountId>,
+    pub amount: U128,
+    pub matched_amount: U128,
+    pub fee: U128,
+    pub tip: U128,
+    pub memo: Option<String>,
+    pub timestamp_ms: u64,
+    pub refunded: bool,
+    pub storage_cost: U128,
+}
+
+type DonationRecords = Vector<DonationRecord>;
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the donations to `recipient`, oldest first. Anonymous donations are listed without their donor.
+    pub fn get_donations(
+        &self,
+        recipient: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<DonationView> {
+        let donations_for_this_recipient = match self.donations.get(&recipient) {
+            Some(donations_for_this_recipient) => donations_for_this_recipient,
+            None => return vec![],
+        };
+        donations_for_this_recipient
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_DONATIONS_LIMIT) as usize)
+            .map(|record| DonationView {
+                donor: if record.anonymous {
+                    None
+                } else {
+                    Some(record.donor)
+                },
+                amount: U128(record.amount),
+                matched_amount: U128(record.matched_amount),
+                fee: U128(record.fee),
+                tip: U128(record.tip),
+                memo: record.memo,
+                timestamp_ms: record.timestamp_ms,
+                refunded: record.refunded,
+                storage_cost: U128(record.storage_cost),
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Panics if the memo (or pledge message) is too long. Otherwise returns the cost of storing it. (A donation's memo is instead paid for as part of the donation's measured storage; see send_donation.)
+    pub(crate) fn validate_memo(memo: &Option<String>) -> Amount {
+        match memo {
+            Some(memo) => {
+                assert!(
+                    memo.len() <= MAX_MEMO_LENGTH,
+                    "Memos can be at most {} bytes long.",
+                    MAX_MEMO_LENGTH
+                );
+                storage_cost(memo.len())
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns the index of the new record, which `on_donate` uses to mark it as refunded if the transfer fails.
+    pub(crate) fn add_donation_record(
+        &mut self,
+        recipient: &RecipientAccountId,
+        record: &DonationRecord,
+    ) -> u64 {
+        let mut donations_for_this_recipient = self.donations.get(recipient).unwrap_or_else(|| {
+            DonationRecords::new(StorageKey::DonationsInner {
+                hash: hash_account_id(&recipient.to_string()),
+            })
+        });
+        donations_for_this_recipient.push(record);
+        self.donations
+            .insert(recipient, &donations_for_this_recipient);
+        donations_for_this_recipient.len() - 1
+    }
+
+    /// Returns the tip of the donation at `index`, so that a refund can return it.
+    pub(crate) fn get_donation_tip(&self, recipient: &RecipientAccountId, index: u64) -> Amount {
+        self.donations
+            .get(recipient)
+            .and_then(|donations_for_this_recipient| donations_for_this_recipient.get(index))
+            .map(|record| record.tip)
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn get_donation_storage_cost(
+        &self,
+        recipient: &RecipientAccountId,
+        index: u64,
+    ) -> Amount {
+        self.donations
+            .get(recipient)
+            .and_then(|donations_for_this_recipient| donations_for_this_recipient.get(index))
+            .map(|record| record.storage_cost)
+            .unwrap_or(0)
+    }
+
+    /// Fills in the storage cost once it has been measured. (The record has a fixed size, so this doesn't change the cost.)
+    pub(crate) fn set_donation_storage_cost(
+        &mut self,
+        recipient: &RecipientAccountId,
+        index: u64,
+        storage_cost: Amount,
+    ) {
+        self.update_donation_record(recipient, index, |record| {
+            record.storage_cost = storage_cost
+        });
+    }
+
+    /// Adds a match that was applied after the donation was recorded (see eligibility_checks.rs) to the donation's record.
+    pub(crate) fn add_match_to_donation_record(
+        &mut self,
+        recipient: &RecipientAccountId,
+        index: u64,
+        matched_amount: Amount,
+        fee: Amount,
+    ) {
+        self.update_donation_record(recipient, index, |record| {
+            record.matched_amount += matched_amount;
+            record.fee += fee;
+        });
+    }
+
+    /// Reverses `add_match_to_donation_record` after a failed transfer.
+    pub(crate) fn subtract_match_from_donation_record(
+        &mut self,
+        recipient: &RecipientAccountId,
+        index: u64,
+        matched_amount: Amount,
+        fee: Amount,
+    ) {
+        self.update_donation_record(recipient, index, |record| {
+            record.matched_amount -= matched_amount;
+            record.fee -= fee;
+        });
+    }
+
+    pub(crate) fn get_donation_employer_match(
+        &self,
+        recipient: &RecipientAccountId,
+        index: u64,
+    ) -> Option<(AccountId, Amount)> {
+        self.donations
+            .get(recipient)
+            .and_then(|donations_for_this_recipient| donations_for_this_recipient.get(index))
+            .and_then(|record| record.employer_match)
+    }
+
+    pub(crate) fn mark_donation_refunded(&mut self, recipient: &RecipientAccountId, index: u64) {
+        self.update_donation_record(recipient, index, |record| record.refunded = true);
+    }
+
+    fn update_donation_record<F: FnOnce(&mut DonationRecord)>(
+        &mut self,
+        recipient: &RecipientAccountId,
+        index: u64,
+        update: F,
+    ) {
+        if let Some(mut donations_for_this_recipient) = self.donations.get(recipient) {
+            if let Some(mut record) = donations_for_this_recipient.get(index) {
+                update(&mut record);
+                donations_for_this_recipient.replace(index, &record);
+            }
+        }
+    }
+}
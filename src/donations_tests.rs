@@ -0,0 +1,144 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod donations_tests {
+    use crate::donations::{DonationView, MAX_MEMO_LENGTH};
+    use crate::generic::{near_string_to_yocto, storage_cost};
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_donation_memo_is_stored_and_storage_is_charged_to_donor() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let memo = "In memory of Grandma".to_string();
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, Some(memo.clone()), None);
+        set_context(4, false, starting_balance, donation); // 4 = Eugene
+        contract.donate(&recipient, Some(true), None, None);
+
+        let donations = contract.get_donations(recipient.clone(), None, None);
+        let storage_cost_with_memo = donations[0].storage_cost.0;
+        let storage_cost_without_memo = donations[1].storage_cost.0;
+        assert!(storage_cost_with_memo >= storage_cost_without_memo + storage_cost(memo.len()));
+        assert!(storage_cost_without_memo > 0); // A record, and new stats and leaderboard entries.
+        assert_eq!(
+            donations,
+            vec![
+                DonationView {
+                    donor: Some(accounts(3)),
+                    amount: U128(donation),
+                    matched_amount: U128(donation),
+                    fee: U128(0),
+                    tip: U128(0),
+                    memo: Some(memo),
+                    timestamp_ms: 0,
+                    refunded: false,
+                    storage_cost: U128(storage_cost_with_memo),
+                },
+                DonationView {
+                    donor: None,
+                    amount: U128(donation),
+                    matched_amount: U128(donation),
+                    fee: U128(0),
+                    tip: U128(0),
+                    memo: None,
+                    timestamp_ms: 0,
+                    refunded: false,
+                    storage_cost: U128(storage_cost_without_memo),
+                }
+            ]
+        );
+        assert_eq!(
+            contract.get_reserve(),
+            U128(storage_cost_with_memo + storage_cost_without_memo)
+        );
+        assert_eq!(
+            contract
+                .get_donations(recipient.clone(), Some(1), Some(1))
+                .len(),
+            1
+        );
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(4),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            true,
+            1,
+        );
+        let donations = contract.get_donations(recipient, None, None);
+        assert!(!donations[0].refunded);
+        assert!(donations[1].refunded);
+    }
+
+    #[test]
+    #[should_panic(expected = "yoctoNEAR is required to cover storage.")]
+    fn test_donation_must_cover_its_storage() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        set_context(1, false, starting_balance, starting_balance); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, 1); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memos can be at most 256 bytes long.")]
+    fn test_donation_memo_too_long() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        set_context(1, false, starting_balance, starting_balance); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, starting_balance); // 3 = Danny
+        contract.donate(
+            &recipient,
+            None,
+            Some("a".repeat(MAX_MEMO_LENGTH + 1)),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_pledge_message_is_shown_by_get_commitment_details() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let message = "Double your impact!".to_string();
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, Some(message.clone()), None);
+        assert_eq!(
+            contract
+                .get_stats(Some(recipient.clone()))
+                .outstanding_commitments,
+            U128(offer - storage_cost(message.len()))
+        );
+        assert_eq!(
+            contract.get_commitment_details(&recipient),
+            "{\"bob\":{\"amount\":\"0.2998 Ⓝ\",\"message\":\"Double your impact!\"}}".to_string()
+        );
+
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "1 Ⓝ".to_string(), None);
+        set_context(1, false, starting_balance, offer);
+        contract.offer_matching_funds(&recipient, None, None);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+// Eligibility checks let a matcher only match donors who hold something on another contract, such as their community's NFT or at least some amount of their token. Since that can only be known by calling the other contract, such a matcher's portion is skipped when the donation is first matched and is applied later (in `on_eligibility_check`) if the donor turns out to be eligible. If the check fails for any reason, the donation simply isn't matched by that matcher.
+
+use crate::gas::{
+    GAS_FOR_ELIGIBILITY_CHECK, GAS_FOR_ON_ELIGIBILITY_CHECK, GAS_FOR_ON_ELIGIBLE_MATCH,
+};
+use crate::generic::{did_promise_succeed, storage_cost_since, yocto_to_near_string};
+use crate::leaderboard::LeaderboardKind;
+use crate::{
+    Amount, Contract, ContractExt, InMemoryMatcherAmountMap, MatcherAccountId, RecipientAccountId,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json::json;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise, PromiseResult};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum EligibilityCheckMethod {
+    NftSupplyForOwner, // NEP-181 (NFT enumeration)
+    FtBalanceOf,       // NEP-141 (fungible tokens)
+}
+
+impl EligibilityCheckMethod {
+    fn name(&self) -> &'static str {
+        match self {
+            EligibilityCheckMethod::NftSupplyForOwner => "nft_supply_for_owner",
+            EligibilityCheckMethod::FtBalanceOf => "ft_balance_of",
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EligibilityCheck {
+    pub contract_id: AccountId,
+    pub method: EligibilityCheckMethod,
+    pub min_balance: u128, // The donor is eligible if the method returns at least this much.
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EligibilityCheckView {
+    pub contract_id: AccountId,
+    pub method: EligibilityCheckMethod,
+    pub min_balance: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by a matcher to only match donations to `recipient` by donors for whom `method` ("nft_supply_for_owner" or "ft_balance_of") of `contract_id` returns at least `min_balance`. Replaces any earlier check.
+    /// While part of the commitment is locked, a check can't be set (since that could make the locked part unusable), only removed.
+    /// The attached deposit must cover the cost of storing the check and goes to this contract's reserve.
+    #[payable] // Public - People can attach money
+    pub fn set_eligibility_check(
+        &mut self,
+        recipient: AccountId,
+        contract_id: AccountId,
+        method: String,
+        min_balance: U128,
+    ) {
+        let matcher = env::signer_account_id();
+        assert!(
+            self.get_locked_amount(&recipient, &matcher) == 0,
+            "An eligibility check can't be set while part of the commitment of {} to {} is locked.",
+            matcher,
+            recipient
+        );
+        self.assert_room_for_eligibility_check(&recipient, &matcher);
+        let method = match method.as_str() {
+            "nft_supply_for_owner" => EligibilityCheckMethod::NftSupplyForOwner,
+            "ft_balance_of" => EligibilityCheckMethod::FtBalanceOf,
+            _ => panic!("The method must be nft_supply_for_owner or ft_balance_of."),
+        };
+        let check = EligibilityCheck {
+            contract_id,
+            method,
+            min_balance: min_balance.0,
+        };
+        let storage_usage_before = env::storage_usage();
+        self.eligibility_checks
+            .insert(&(recipient, matcher), &check);
+        let check_storage_cost = storage_cost_since(storage_usage_before);
+        assert!(
+            env::attached_deposit() >= check_storage_cost,
+            "Attaching at least {} yoctoNEAR is required.",
+            check_storage_cost
+        );
+        self.add_to_reserve(env::attached_deposit());
+    }
+
+    pub fn remove_eligibility_check(&mut self, recipient: AccountId) {
+        let matcher = env::signer_account_id();
+        self.eligibility_checks
+            .remove(&(recipient, matcher))
+            .expect("There is no eligibility check for this recipient.");
+    }
+
+    pub fn get_eligibility_check(
+        &self,
+        recipient: AccountId,
+        matcher: MatcherAccountId,
+    ) -> Option<EligibilityCheckView> {
+        self.eligibility_checks
+            .get(&(recipient, matcher))
+            .map(|check| EligibilityCheckView {
+                contract_id: check.contract_id,
+                method: check.method,
+                min_balance: U128(check.min_balance),
+            })
+    }
+
+    /// Receives the result of a matcher's eligibility check for a donation. If the donor is eligible, matches the donation out of the matcher's current commitment (just like `donate` would have) and transfers the matched amount to `recipient` (or holds it; see goals.rs and milestones.rs).
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_eligibility_check(
+        &mut self,
+        recipient: RecipientAccountId,
+        matcher: MatcherAccountId,
+        donor: AccountId,
+        donation_amount: Amount,
+        anonymous: bool,
+        donation_index: u64,
+    ) {
+        let check = match self
+            .eligibility_checks
+            .get(&(recipient.clone(), matcher.clone()))
+        {
+            Some(check) => check,
+            None => return, // The matcher removed their check in the meantime.
+        };
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(result) => {
+                near_sdk::serde_json::from_slice::<U128>(&result).map(|balance| balance.0)
+            }
+            _ => {
+                log!("The eligibility check of {} failed.", matcher);
+                return;
+            }
+        };
+        match balance {
+            Ok(balance) if balance >= check.min_balance => {}
+            Ok(_) => {
+                log!("The donor is not eligible for matching by {}.", matcher);
+                return;
+            }
+            Err(_) => {
+                log!(
+                    "The eligibility check of {} returned an unexpected result.",
+                    matcher
+                );
+                return;
+            }
+        }
+        let existing_commitment = self.get_commitment(&recipient, &matcher);
+        let matched_amount = donation_amount.min(existing_commitment);
+        if matched_amount == 0 {
+            return;
+        }
+        self.set_matcher_amount(&recipient, &matcher, existing_commitment - matched_amount);
+        self.add_to_leaderboards(
+            &recipient,
+            &matcher,
+            matched_amount,
+            LeaderboardKind::Matchers,
+        );
+        self.record_donation(&recipient, &donor, 0, matched_amount);
+        self.total_escrowed -= matched_amount;
+        let matched_amounts = InMemoryMatcherAmountMap::from([(matcher.clone(), matched_amount)]);
+        let held = self.hold_for_goal(&recipient, &donor, 0, &matched_amounts, anonymous)
+            || self.hold_for_milestones(&recipient, &donor, 0, &matched_amounts, anonymous);
+        let fee = if held {
+            0 // Charged when the goal's funds are released (or a milestone is approved).
+        } else {
+            self.calculate_fee(0, matched_amount)
+        };
+        self.add_match_to_donation_record(&recipient, donation_index, matched_amount, fee);
+        log!(
+            "{} is eligible, so {} will send a matching donation of {} to {}.",
+            if anonymous {
+                "The donor"
+            } else {
+                donor.as_str()
+            },
+            matcher,
+            yocto_to_near_string(&matched_amount),
+            recipient
+        );
+        if held {
+            return;
+        }
+        self.pending_fees += fee;
+        self.transfer_from_escrow(&recipient, matched_amount - fee)
+            .then(
+                Self::ext(env::current_account_id()) // escrow contract name
+                    .with_static_gas(GAS_FOR_ON_ELIGIBLE_MATCH)
+                    .on_eligible_match(recipient, matcher, matched_amount, fee, donation_index),
+            );
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_eligible_match(
+        &mut self,
+        recipient: RecipientAccountId,
+        matcher: MatcherAccountId,
+        matched_amount: Amount,
+        fee: Amount,
+        donation_index: u64,
+    ) {
+        let succeeded = did_promise_succeed();
+        self.settle_pending_fee(fee, succeeded);
+        if !succeeded {
+            // If transfer failed, give the matcher back what was taken from their commitment:
+            self.add_to_commitment(&recipient, &matcher, matched_amount);
+            self.total_escrowed += matched_amount;
+            self.subtract_from_leaderboards(
+                &recipient,
+                &matcher,
+                matched_amount,
+                LeaderboardKind::Matchers,
+            );
+            self.unrecord_matching(&recipient, matched_amount);
+            self.subtract_match_from_donation_record(
+                &recipient,
+                donation_index,
+                matched_amount,
+                fee,
+            );
+            log!(
+                "Transfer to {} failed. Returning {} to the commitment of {}.",
+                recipient,
+                yocto_to_near_string(&matched_amount),
+                matcher
+            );
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn has_eligibility_check(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) -> bool {
+        self.eligibility_checks
+            .contains_key(&(recipient.clone(), matcher.clone()))
+    }
+
+    /// Calls the eligibility check of each of `matchers` for this donation, with `on_eligibility_check` as the callback.
+    pub(crate) fn check_eligibility(
+        &self,
+        recipient: &RecipientAccountId,
+        matchers: Vec<MatcherAccountId>,
+        donor: &AccountId,
+        donation_amount: Amount,
+        anonymous: bool,
+        donation_index: u64,
+    ) {
+        for matcher in matchers {
+            let check = self
+                .eligibility_checks
+                .get(&(recipient.clone(), matcher.clone()))
+                .unwrap();
+            Promise::new(check.contract_id)
+                .function_call(
+                    check.method.name().to_string(),
+                    json!({ "account_id": donor }).to_string().into_bytes(),
+                    0,
+                    GAS_FOR_ELIGIBILITY_CHECK,
+                )
+                .then(
+                    Self::ext(env::current_account_id()) // escrow contract name
+                        .with_static_gas(GAS_FOR_ON_ELIGIBILITY_CHECK)
+                        .on_eligibility_check(
+                            recipient.clone(),
+                            matcher,
+                            donor.clone(),
+                            donation_amount,
+                            anonymous,
+                            donation_index,
+                        ),
+                );
+        }
+    }
+}
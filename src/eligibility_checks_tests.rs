@@ -0,0 +1,153 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod eligibility_checks_tests {
+    use crate::gas::{GAS_FOR_ELIGIBILITY_CHECK, GAS_FOR_ON_ELIGIBILITY_CHECK};
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context, set_context_at};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+
+    const NFT_CONTRACT: &str = "nft.community.near";
+
+    /// Bob (with an eligibility check) and Charlie (without) each commit 0.3 Ⓝ to Alice, and Danny donates 0.1 Ⓝ.
+    fn setup() -> Contract {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(
+            1,
+            false,
+            starting_balance,
+            near_string_to_yocto(&"0.01".to_string()),
+        );
+        contract.set_eligibility_check(
+            recipient.clone(),
+            NFT_CONTRACT.parse().unwrap(),
+            "nft_supply_for_owner".to_string(),
+            U128(1),
+        );
+        set_context(2, false, starting_balance, offer); // 2 = Charlie
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(
+            3,
+            false,
+            starting_balance,
+            near_string_to_yocto(&"0.1".to_string()),
+        ); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.3 Ⓝ\",\"charlie\":\"0.2 Ⓝ\"}".to_string()
+        ); // Bob's portion waits for his check.
+        contract
+    }
+
+    fn on_eligibility_check(contract: &mut Contract, result: PromiseResult) {
+        set_callback_context(result);
+        contract.on_eligibility_check(
+            accounts(0),
+            accounts(1),
+            accounts(3),
+            near_string_to_yocto(&"0.1".to_string()),
+            false,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_eligible_donor_is_matched_in_callback() {
+        let mut contract = setup();
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        on_eligibility_check(&mut contract, PromiseResult::Successful(b"\"2\"".to_vec()));
+        assert_eq!(
+            contract.get_commitments(&accounts(0)),
+            "{\"bob\":\"0.2 Ⓝ\",\"charlie\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[0].matched_amount,
+            U128(2 * donation)
+        );
+        assert_eq!(
+            contract.get_stats(Some(accounts(0))).total_matched,
+            U128(2 * donation)
+        );
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_eligible_match(accounts(0), accounts(1), donation, 0, 0);
+        assert_eq!(
+            contract.get_commitments(&accounts(0)),
+            "{\"bob\":\"0.3 Ⓝ\",\"charlie\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[0].matched_amount,
+            U128(donation)
+        );
+        let report = contract.check_invariants();
+        assert_eq!(report.total_escrowed, report.sum_of_commitments);
+    }
+
+    #[test]
+    fn test_ineligible_or_failed_check_is_not_matched() {
+        let mut contract = setup();
+        let unchanged = "{\"bob\":\"0.3 Ⓝ\",\"charlie\":\"0.2 Ⓝ\"}".to_string();
+        on_eligibility_check(&mut contract, PromiseResult::Successful(b"\"0\"".to_vec()));
+        assert_eq!(contract.get_commitments(&accounts(0)), unchanged);
+        on_eligibility_check(&mut contract, PromiseResult::Failed);
+        assert_eq!(contract.get_commitments(&accounts(0)), unchanged);
+        on_eligibility_check(&mut contract, PromiseResult::Successful(b"{}".to_vec()));
+        assert_eq!(contract.get_commitments(&accounts(0)), unchanged);
+    }
+
+    #[test]
+    fn test_eligibility_checks_need_more_gas() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        set_context(
+            1,
+            false,
+            starting_balance,
+            near_string_to_yocto(&"0.3".to_string()),
+        ); // 1 = Bob
+        contract.offer_matching_funds(&accounts(0), None, None);
+        let gas_without_checks = contract.estimate_donate_gas(accounts(0), None);
+        set_context(
+            1,
+            false,
+            starting_balance,
+            near_string_to_yocto(&"0.01".to_string()),
+        );
+        contract.set_eligibility_check(
+            accounts(0),
+            NFT_CONTRACT.parse().unwrap(),
+            "ft_balance_of".to_string(),
+            U128(1),
+        );
+        assert_eq!(
+            contract.estimate_donate_gas(accounts(0), None),
+            gas_without_checks + GAS_FOR_ELIGIBILITY_CHECK + GAS_FOR_ON_ELIGIBILITY_CHECK
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "An eligibility check can't be set while part of the commitment of bob to alice is locked."
+    )]
+    fn test_check_cannot_be_set_on_a_locked_commitment() {
+        let mut contract = Contract::new();
+        let now = 1_700_000_000_000;
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), now); // 1 = Bob
+        contract.offer_matching_funds(&accounts(0), None, Some(now + 1_000_000));
+        set_context_at(1, near_string_to_yocto(&"0.01".to_string()), now);
+        contract.set_eligibility_check(
+            accounts(0),
+            NFT_CONTRACT.parse().unwrap(),
+            "nft_supply_for_owner".to_string(),
+            U128(1),
+        );
+    }
+}
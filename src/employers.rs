@@ -0,0 +1,273 @@
+// Employer matching programs: an employer registers its employees (each of whom must have accepted that employer first) and matches each employee's donations (to any recipient, or only to the eligible ones) up to a yearly cap per employee, out of the employer's vault (see vaults.rs). Unlike commitments, this isn't tied to a recipient, so it is applied in `send_donation` on top of the recipient's matchers.
+
+use crate::generic::{self, near_string_to_yocto, storage_cost_since, yocto_to_near_string};
+use crate::leaderboard::LeaderboardKind;
+use crate::{Amount, Contract, ContractExt, RecipientAccountId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId};
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EmployerProgram {
+    pub annual_cap_per_employee: Amount,
+    pub eligible_recipients: Option<Vec<RecipientAccountId>>, // None means that donations to any recipient are matched.
+}
+
+/// How much of an employee's donations their employer has matched in a (UTC calendar) year.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EmployeeMatchUsage {
+    pub employer: AccountId,
+    pub year: i64,
+    pub matched: Amount,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmployerProgramView {
+    pub annual_cap_per_employee: U128,
+    pub eligible_recipients: Option<Vec<RecipientAccountId>>,
+    pub remaining_balance: U128, // The employer's vault balance.
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmployeeMatchView {
+    pub employer: AccountId,
+    pub matched_this_year: U128,
+    pub remaining_this_year: U128, // Limited by the cap (not by the employer's vault balance).
+}
+
+/// Converts a Unix timestamp (in milliseconds) to its year in UTC. See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn utc_year(timestamp_ms: u64) -> i64 {
+    let days_since_0000_03_01 = (timestamp_ms / MS_PER_DAY) as i64 + 719_468;
+    let era = days_since_0000_03_01.div_euclid(146_097);
+    let day_of_era = days_since_0000_03_01 - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_starting_in_march = (5 * day_of_year + 2) / 153;
+    let is_january_or_february = month_starting_in_march >= 10;
+    year_of_era + era * 400 + i64::from(is_january_or_february)
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by an employer to match each registered employee's donations up to `annual_cap_per_employee` per calendar year (UTC), optionally only to `eligible_recipients`. Replaces any earlier settings. Matches are paid out of the employer's vault, which also pays for storing these settings.
+    /// annual_cap_per_employee is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn set_employer_program(
+        &mut self,
+        annual_cap_per_employee: generic::FormattedNearString,
+        eligible_recipients: Option<Vec<RecipientAccountId>>,
+    ) {
+        let employer = env::signer_account_id();
+        let program = EmployerProgram {
+            annual_cap_per_employee: near_string_to_yocto(&annual_cap_per_employee),
+            eligible_recipients,
+        };
+        let storage_usage_before = env::storage_usage();
+        self.employer_programs.insert(&employer, &program);
+        let program_storage_cost = storage_cost_since(storage_usage_before);
+        self.debit_vault(&employer, program_storage_cost);
+        self.add_to_reserve(program_storage_cost);
+    }
+
+    pub fn get_employer_program(&self, employer: AccountId) -> Option<EmployerProgramView> {
+        self.employer_programs
+            .get(&employer)
+            .map(|program| EmployerProgramView {
+                annual_cap_per_employee: U128(program.annual_cap_per_employee),
+                eligible_recipients: program.eligible_recipients,
+                remaining_balance: self.get_vault_balance(employer),
+            })
+    }
+
+    /// Called by an employee to allow `employer` to register them (see `register_employees`).
+    pub fn accept_employer(&mut self, employer: AccountId) {
+        let employee = env::signer_account_id();
+        log!("{} now accepts {} as their employer.", &employee, &employer);
+        self.employer_acceptances.insert(&(employee, employer));
+    }
+
+    /// Called by an employee to stop `employer` from registering them, which also removes them from that employer's registry.
+    pub fn revoke_employer(&mut self, employer: AccountId) {
+        let employee = env::signer_account_id();
+        log!(
+            "{} no longer accepts {} as their employer.",
+            &employee,
+            &employer
+        );
+        if self.employers_by_employee.get(&employee) == Some(employer.clone()) {
+            self.employers_by_employee.remove(&employee);
+            self.employee_match_usage.remove(&employee);
+        }
+        self.employer_acceptances.remove(&(employee, employer));
+    }
+
+    /// Called by an employer (after `set_employer_program`) to add employees to its registry. Each employee must have called `accept_employer` first. The cost of storing them is taken from the employer's vault.
+    pub fn register_employees(&mut self, employees: Vec<AccountId>) {
+        let employer = env::signer_account_id();
+        assert!(
+            self.employer_programs.contains_key(&employer),
+            "Call set_employer_program first."
+        );
+        let storage_usage_before = env::storage_usage();
+        for employee in employees.iter() {
+            match self.employers_by_employee.get(employee) {
+                Some(existing_employer) if existing_employer == employer => {}
+                Some(existing_employer) => panic!(
+                    "{} is already registered with {}.",
+                    employee, existing_employer
+                ),
+                None => {
+                    assert!(
+                        self.employer_acceptances
+                            .contains(&(employee.clone(), employer.clone())),
+                        "{} has not accepted {} as their employer.",
+                        employee,
+                        employer
+                    );
+                    self.employers_by_employee.insert(employee, &employer);
+                    // Stored now (rather than on the first match) so that the employer pays for it:
+                    self.employee_match_usage.insert(
+                        employee,
+                        &EmployeeMatchUsage {
+                            employer: employer.clone(),
+                            year: utc_year(env::block_timestamp_ms()),
+                            matched: 0,
+                        },
+                    );
+                }
+            }
+        }
+        let employees_storage_cost = storage_cost_since(storage_usage_before);
+        self.debit_vault(&employer, employees_storage_cost);
+        self.add_to_reserve(employees_storage_cost);
+    }
+
+    pub fn remove_employees(&mut self, employees: Vec<AccountId>) {
+        let employer = env::signer_account_id();
+        for employee in employees.iter() {
+            if self.employers_by_employee.get(employee) == Some(employer.clone()) {
+                self.employers_by_employee.remove(employee);
+                self.employee_match_usage.remove(employee);
+            }
+        }
+    }
+
+    pub fn get_employee_match(&self, employee: AccountId) -> Option<EmployeeMatchView> {
+        let employer = self.employers_by_employee.get(&employee)?;
+        let program = self.employer_programs.get(&employer)?;
+        let matched_this_year = self.get_matched_this_year(&employee, &employer);
+        Some(EmployeeMatchView {
+            employer,
+            matched_this_year: U128(matched_this_year),
+            remaining_this_year: U128(
+                program
+                    .annual_cap_per_employee
+                    .saturating_sub(matched_this_year),
+            ),
+        })
+    }
+}
+
+impl Contract {
+    fn get_matched_this_year(&self, employee: &AccountId, employer: &AccountId) -> Amount {
+        match self.employee_match_usage.get(employee) {
+            Some(usage)
+                if &usage.employer == employer
+                    && usage.year == utc_year(env::block_timestamp_ms()) =>
+            {
+                usage.matched
+            }
+            _ => 0,
+        }
+    }
+
+    /// If `donor` is a registered employee, matches as much of the donation as their employer's program allows (out of the employer's vault) and returns the employer and the matched amount.
+    pub(crate) fn apply_employer_match(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+        anonymous: bool,
+    ) -> Option<(AccountId, Amount)> {
+        let employer = self.employers_by_employee.get(donor)?;
+        let program = self.employer_programs.get(&employer)?;
+        if let Some(eligible_recipients) = &program.eligible_recipients {
+            if !eligible_recipients.contains(recipient) {
+                return None;
+            }
+        }
+        let matched_this_year = self.get_matched_this_year(donor, &employer);
+        let matched_amount = donation_amount
+            .min(
+                program
+                    .annual_cap_per_employee
+                    .saturating_sub(matched_this_year),
+            )
+            .min(self.get_vault_balance(employer.clone()).0);
+        if matched_amount == 0 {
+            return None;
+        }
+        self.debit_vault(&employer, matched_amount);
+        self.employee_match_usage.insert(
+            donor,
+            &EmployeeMatchUsage {
+                employer: employer.clone(),
+                year: utc_year(env::block_timestamp_ms()),
+                matched: matched_this_year + matched_amount,
+            },
+        );
+        self.record_donation(recipient, donor, 0, matched_amount);
+        if !anonymous {
+            // Naming the employer (on the leaderboard or in the log) would hint at who the donor is.
+            self.add_to_leaderboards(
+                recipient,
+                &employer,
+                matched_amount,
+                LeaderboardKind::Matchers,
+            );
+            log!(
+                "{} will send a matching donation of {} to {}.",
+                employer,
+                yocto_to_near_string(&matched_amount),
+                recipient
+            );
+        }
+        Some((employer, matched_amount))
+    }
+
+    /// Reverses `apply_employer_match` after a failed transfer.
+    pub(crate) fn unapply_employer_match(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        employer: &AccountId,
+        matched_amount: Amount,
+        anonymous: bool,
+    ) {
+        self.credit_vault(employer, matched_amount);
+        if let Some(mut usage) = self.employee_match_usage.get(donor) {
+            if &usage.employer == employer {
+                usage.matched = usage.matched.saturating_sub(matched_amount);
+                self.employee_match_usage.insert(donor, &usage);
+            }
+        }
+        self.unrecord_matching(recipient, matched_amount);
+        if !anonymous {
+            self.subtract_from_leaderboards(
+                recipient,
+                employer,
+                matched_amount,
+                LeaderboardKind::Matchers,
+            );
+        }
+    }
+
+    pub(crate) fn is_registered_employee(&self, account: &AccountId) -> bool {
+        self.employers_by_employee.contains_key(account)
+    }
+}
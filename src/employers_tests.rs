@@ -0,0 +1,204 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod employers_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::leaderboard::LeaderboardEntry;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context_at};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    const LAST_DAY_OF_2024: u64 = 1_735_603_200_000; // 2024-12-31T00:00:00Z
+    const FIRST_DAY_OF_2025: u64 = 1_735_689_600_000; // 2025-01-01T00:00:00Z
+
+    /// Eugene (the employer) matches Danny's donations (Danny having accepted Eugene as his employer) up to 0.15 Ⓝ per year. Bob is a regular matcher of Alice and Charlie.
+    fn setup(eligible_recipients: Option<Vec<near_sdk::AccountId>>) -> Contract {
+        let mut contract = Contract::new();
+        let offer = near_string_to_yocto(&"1".to_string());
+        set_context_at(1, offer, LAST_DAY_OF_2024); // 1 = Bob
+        contract.offer_matching_funds(&accounts(0), None, None); // 0 = Alice
+        set_context_at(1, offer, LAST_DAY_OF_2024);
+        contract.offer_matching_funds(&accounts(2), None, None); // 2 = Charlie
+        set_context_at(3, 0, LAST_DAY_OF_2024); // 3 = Danny
+        contract.accept_employer(accounts(4));
+        set_context_at(4, near_string_to_yocto(&"1".to_string()), LAST_DAY_OF_2024); // 4 = Eugene
+        contract.deposit_to_vault();
+        contract.set_employer_program("0.15".to_string(), eligible_recipients);
+        contract.register_employees(vec![accounts(3)]); // 3 = Danny
+        contract
+    }
+
+    fn donate(contract: &mut Contract, donor_index: usize, recipient_index: usize, ts_ms: u64) {
+        set_context_at(donor_index, near_string_to_yocto(&"0.1".to_string()), ts_ms);
+        contract.donate(&accounts(recipient_index), None, None, None);
+    }
+
+    #[test]
+    fn test_employer_matches_up_to_annual_cap() {
+        let mut contract = setup(None);
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let cap = near_string_to_yocto(&"0.15".to_string());
+        let vault_after_storage = contract.get_vault_balance(accounts(4)).0;
+
+        donate(&mut contract, 3, 0, LAST_DAY_OF_2024);
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[0].matched_amount,
+            U128(2 * donation)
+        ); // Bob and Eugene
+        donate(&mut contract, 3, 2, LAST_DAY_OF_2024); // Any recipient counts toward the cap.
+        assert_eq!(
+            contract.get_donations(accounts(2), None, None)[0].matched_amount,
+            U128(donation + (cap - donation))
+        );
+        let employee_match = contract.get_employee_match(accounts(3)).unwrap();
+        assert_eq!(employee_match.matched_this_year, U128(cap));
+        assert_eq!(employee_match.remaining_this_year, U128(0));
+        assert_eq!(
+            contract.get_vault_balance(accounts(4)),
+            U128(vault_after_storage - cap)
+        );
+
+        donate(&mut contract, 5, 0, LAST_DAY_OF_2024); // 5 = Fargo, not an employee
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[1].matched_amount,
+            U128(donation)
+        );
+
+        set_context_at(3, 0, FIRST_DAY_OF_2025);
+        assert_eq!(
+            contract
+                .get_employee_match(accounts(3))
+                .unwrap()
+                .remaining_this_year,
+            U128(cap)
+        );
+        donate(&mut contract, 3, 0, FIRST_DAY_OF_2025);
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[2].matched_amount,
+            U128(2 * donation)
+        );
+        assert!(contract.check_invariants().discrepancies.is_empty());
+    }
+
+    #[test]
+    fn test_employer_match_only_for_eligible_recipients() {
+        let mut contract = setup(Some(vec![accounts(2)]));
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        donate(&mut contract, 3, 0, LAST_DAY_OF_2024);
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[0].matched_amount,
+            U128(donation)
+        );
+        donate(&mut contract, 3, 2, LAST_DAY_OF_2024);
+        assert_eq!(
+            contract.get_donations(accounts(2), None, None)[0].matched_amount,
+            U128(2 * donation)
+        );
+    }
+
+    #[test]
+    fn test_failed_donation_returns_employer_match() {
+        let mut contract = setup(None);
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let vault_before = contract.get_vault_balance(accounts(4));
+        donate(&mut contract, 3, 0, LAST_DAY_OF_2024);
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &accounts(0),
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            false,
+            0,
+        );
+        assert_eq!(contract.get_vault_balance(accounts(4)), vault_before);
+        set_context_at(3, 0, LAST_DAY_OF_2024);
+        assert_eq!(
+            contract
+                .get_employee_match(accounts(3))
+                .unwrap()
+                .matched_this_year,
+            U128(0)
+        );
+        assert_eq!(contract.get_stats(Some(accounts(0))).total_matched, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "danny is already registered with eugene.")]
+    fn test_employee_can_only_have_one_employer() {
+        let mut contract = setup(None);
+        set_context_at(5, near_string_to_yocto(&"1".to_string()), LAST_DAY_OF_2024); // 5 = Fargo
+        contract.deposit_to_vault();
+        contract.set_employer_program("1".to_string(), None);
+        contract.register_employees(vec![accounts(3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "danny has not accepted fargo as their employer.")]
+    fn test_employee_must_accept_the_employer() {
+        let mut contract = Contract::new();
+        set_context_at(5, near_string_to_yocto(&"1".to_string()), LAST_DAY_OF_2024); // 5 = Fargo
+        contract.deposit_to_vault();
+        contract.set_employer_program("1".to_string(), None);
+        contract.register_employees(vec![accounts(3)]); // 3 = Danny
+    }
+
+    #[test]
+    fn test_registry_storage_is_paid_from_the_employer_vault() {
+        let contract = setup(None);
+        let storage_cost = contract.get_reserve().0;
+        assert!(storage_cost > 0);
+        assert_eq!(
+            contract.get_vault_balance(accounts(4)),
+            U128(near_string_to_yocto(&"1".to_string()) - storage_cost)
+        );
+    }
+
+    #[test]
+    fn test_anonymous_donation_does_not_log_the_employer() {
+        let mut contract = setup(None);
+        set_context_at(
+            3,
+            near_string_to_yocto(&"0.1".to_string()),
+            LAST_DAY_OF_2024,
+        ); // 3 = Danny
+        contract.donate(&accounts(0), Some(true), None, None);
+        assert_eq!(
+            contract.get_donations(accounts(0), None, None)[0].matched_amount,
+            U128(2 * near_string_to_yocto(&"0.1".to_string()))
+        ); // Bob and Eugene
+        assert!(!get_logs().iter().any(|log| log.contains("eugene")));
+    }
+
+    #[test]
+    fn test_anonymous_donation_keeps_the_employer_off_the_leaderboards() {
+        let mut contract = setup(None);
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let vault_before = contract.get_vault_balance(accounts(4));
+        set_context_at(3, donation, LAST_DAY_OF_2024); // 3 = Danny
+        contract.donate(&accounts(0), Some(true), None, None);
+        let is_employer = |entry: &LeaderboardEntry| entry.account_id == accounts(4);
+        assert!(!contract
+            .get_top_matchers(Some(accounts(0)))
+            .iter()
+            .any(is_employer));
+        assert!(!contract.get_top_matchers(None).iter().any(is_employer));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &accounts(0),
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            true,
+            0,
+        );
+        assert!(!contract.get_top_matchers(None).iter().any(is_employer));
+        assert_eq!(contract.get_vault_balance(accounts(4)), vault_before);
+    }
+}
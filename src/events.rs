@@ -0,0 +1,87 @@
+// Events are logged in the NEP-297 format (https://nomicon.io/Standards/EventsFormat) so that indexers and front-ends can parse them.
+
+use crate::RecipientAccountId;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::{log, AccountId};
+
+pub const EVENT_STANDARD: &str = "donation_matcher";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// The donor is omitted from the events of anonymous donations.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationEventData {
+    pub recipient: RecipientAccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub donor: Option<AccountId>,
+    pub amount: U128,
+    pub matched_amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<U128>, // Omitted when no platform fee was charged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationRefundEventData {
+    pub recipient: RecipientAccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub donor: Option<AccountId>,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommitmentReallocatedEventData {
+    pub matcher: AccountId,
+    pub from_recipient: RecipientAccountId,
+    pub to_recipient: RecipientAccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommitmentTransferredEventData {
+    pub recipient: RecipientAccountId,
+    pub from_matcher: AccountId,
+    pub to_matcher: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    Donation(DonationEventData),
+    DonationRefund(DonationRefundEventData),
+    CommitmentReallocated(CommitmentReallocatedEventData),
+    CommitmentTransferred(CommitmentTransferredEventData),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+impl Event {
+    pub(crate) fn emit(&self) {
+        let event_log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        log!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&event_log).expect("Could not serialize event")
+        );
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod events_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    fn get_event_logs() -> Vec<String> {
+        get_logs()
+            .into_iter()
+            .filter(|log| log.starts_with("EVENT_JSON:"))
+            .collect()
+    }
+
+    #[test]
+    fn test_donation_event() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        assert_eq!(
+            get_event_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"donation_matcher","version":"1.0.0","event":"donation","data":{{"recipient":"alice","donor":"danny","amount":"{donation}","matched_amount":"{donation}"}}}}"#
+            )]
+        );
+    }
+
+    #[test]
+    fn test_anonymous_donation_does_not_reveal_donor() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, Some(true), None, None);
+        assert_eq!(
+            get_event_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"donation_matcher","version":"1.0.0","event":"donation","data":{{"recipient":"alice","amount":"{donation}","matched_amount":"{donation}"}}}}"#
+            )]
+        );
+        assert!(get_logs().iter().all(|log| !log.contains("danny")));
+        assert_eq!(contract.get_top_donors(None), vec![]);
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            true,
+            0,
+        );
+        assert_eq!(
+            get_event_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"donation_matcher","version":"1.0.0","event":"donation_refund","data":{{"recipient":"alice","amount":"{donation}"}}}}"#
+            )]
+        );
+        assert!(get_logs().iter().all(|log| !log.contains("danny")));
+        assert_eq!(contract.get_stats(None).unique_donors, 0);
+    }
+}
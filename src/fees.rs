@@ -0,0 +1,112 @@
+// An optional platform fee, in basis points, on donations and/or matching funds. Fees accrue in this contract until they get withdrawn to the fee recipient.
+// A fee on a transfer that is still in flight is pending (it would be given back if the transfer failed), so it only becomes part of the withdrawable `accrued_fees` once the transfer's callback sees it succeed.
+
+use crate::generic::{did_promise_succeed, yocto_to_near_string};
+use crate::{Amount, Contract, ContractExt, GAS_FOR_ACCOUNT_CALLBACK};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise};
+
+pub const MAX_FEE_BASIS_POINTS: u16 = 500; // 5%. Hard-coded so that donors and matchers can trust that the fee will never be higher.
+const BASIS_POINTS_PER_WHOLE: u128 = 10_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct FeeConfig {
+    pub donation_fee_basis_points: u16,
+    pub matching_fee_basis_points: u16,
+    pub fee_recipient: Option<AccountId>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfigView {
+    pub donation_fee_basis_points: u16,
+    pub matching_fee_basis_points: u16,
+    pub max_fee_basis_points: u16,
+    pub fee_recipient: Option<AccountId>,
+    pub accrued_fees: U128,
+    pub pending_fees: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn set_fee_config(
+        &mut self,
+        donation_fee_basis_points: u16,
+        matching_fee_basis_points: u16,
+        fee_recipient: AccountId,
+    ) {
+        assert!(
+            donation_fee_basis_points <= MAX_FEE_BASIS_POINTS
+                && matching_fee_basis_points <= MAX_FEE_BASIS_POINTS,
+            "Fees can be at most {} basis points.",
+            MAX_FEE_BASIS_POINTS
+        );
+        self.fee_config = FeeConfig {
+            donation_fee_basis_points,
+            matching_fee_basis_points,
+            fee_recipient: Some(fee_recipient),
+        };
+    }
+
+    pub fn get_fee_config(&self) -> FeeConfigView {
+        FeeConfigView {
+            donation_fee_basis_points: self.fee_config.donation_fee_basis_points,
+            matching_fee_basis_points: self.fee_config.matching_fee_basis_points,
+            max_fee_basis_points: MAX_FEE_BASIS_POINTS,
+            fee_recipient: self.fee_config.fee_recipient.clone(),
+            accrued_fees: U128(self.accrued_fees),
+            pending_fees: U128(self.pending_fees),
+        }
+    }
+
+    /// Sends all accrued fees to the fee recipient. Pending fees stay until their transfers succeed.
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn withdraw_fees(&mut self) -> Promise {
+        let fee_recipient = self
+            .fee_config
+            .fee_recipient
+            .clone()
+            .expect("No fee recipient has been set.");
+        let amount = self.accrued_fees;
+        assert!(amount > 0, "There are no fees to withdraw.");
+        self.accrued_fees = 0;
+        log!(
+            "Withdrawing {} of fees to {}.",
+            yocto_to_near_string(&amount),
+            fee_recipient
+        );
+        self.transfer_from_escrow(&fee_recipient, amount).then(
+            Self::ext(env::current_account_id()) // escrow contract name
+                .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                .on_withdraw_fees(amount),
+        )
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_withdraw_fees(&mut self, amount: Amount) {
+        if !did_promise_succeed() {
+            // If transfer failed, the fees are still here:
+            self.accrued_fees += amount;
+        }
+    }
+}
+
+impl Contract {
+    /// Returns the fee charged on a donation of `donation_amount` that got matched with `matched_amount`.
+    pub(crate) fn calculate_fee(&self, donation_amount: Amount, matched_amount: Amount) -> Amount {
+        donation_amount * self.fee_config.donation_fee_basis_points as u128 / BASIS_POINTS_PER_WHOLE
+            + matched_amount * self.fee_config.matching_fee_basis_points as u128
+                / BASIS_POINTS_PER_WHOLE
+    }
+
+    /// Called by the callback of a transfer that was charged `fee`: the fee is no longer pending, and it accrues only if the transfer succeeded.
+    pub(crate) fn settle_pending_fee(&mut self, fee: Amount, transfer_succeeded: bool) {
+        self.pending_fees -= fee;
+        if transfer_succeeded {
+            self.accrued_fees += fee;
+        }
+    }
+}
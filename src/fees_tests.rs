@@ -0,0 +1,158 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod fees_tests {
+    use crate::fees::MAX_FEE_BASIS_POINTS;
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fees_are_recorded_and_reversed_on_rollback() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        contract.set_fee_config(200, 100, accounts(5)); // 5 = Fargo
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+
+        let fee = donation * 2 / 100 + donation / 100;
+        assert_eq!(contract.get_fee_config().pending_fees, U128(fee));
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(0));
+        assert_eq!(
+            contract.get_donations(recipient.clone(), None, None)[0].fee,
+            U128(fee)
+        );
+        assert!(get_logs()
+            .iter()
+            .any(|log| log.contains(&format!(r#""fee":"{}""#, fee))));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            fee,
+            false,
+            0,
+        );
+        assert_eq!(contract.get_fee_config().pending_fees, U128(0));
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(0));
+    }
+
+    #[test]
+    fn test_fees_accrue_once_the_transfer_succeeds() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        contract.set_fee_config(MAX_FEE_BASIS_POINTS, 0, accounts(5)); // 5 = Fargo
+        set_context(1, false, starting_balance, starting_balance); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        let fee = donation * MAX_FEE_BASIS_POINTS as u128 / 10_000;
+
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            fee,
+            false,
+            0,
+        );
+        assert_eq!(contract.get_fee_config().pending_fees, U128(0));
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(fee));
+    }
+
+    #[test]
+    fn test_withdrawing_fees_during_a_pending_transfer() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        contract.set_fee_config(MAX_FEE_BASIS_POINTS, 0, accounts(5)); // 5 = Fargo
+        set_context(1, false, starting_balance, starting_balance); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        let fee = donation * MAX_FEE_BASIS_POINTS as u128 / 10_000;
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            fee,
+            false,
+            0,
+        );
+
+        // A second donation is still being transferred when the fees get withdrawn:
+        set_context(3, false, starting_balance, donation);
+        contract.donate(&recipient, None, None, None);
+        contract.withdraw_fees();
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(0));
+        assert_eq!(contract.get_fee_config().pending_fees, U128(fee));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            fee,
+            false,
+            1,
+        );
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(0));
+        assert_eq!(contract.get_fee_config().pending_fees, U128(0));
+    }
+
+    #[test]
+    fn test_failed_fee_withdrawal_keeps_fees() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        contract.set_fee_config(MAX_FEE_BASIS_POINTS, 0, accounts(5)); // 5 = Fargo
+        set_context(1, false, starting_balance, donation); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        let fee = donation * MAX_FEE_BASIS_POINTS as u128 / 10_000;
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            fee,
+            false,
+            0,
+        );
+
+        contract.withdraw_fees();
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(0));
+        set_callback_context(PromiseResult::Failed);
+        contract.on_withdraw_fees(fee);
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(fee));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fees can be at most 500 basis points.")]
+    fn test_fee_above_cap() {
+        let mut contract = Contract::new();
+        contract.set_fee_config(0, MAX_FEE_BASIS_POINTS + 1, accounts(5)); // 5 = Fargo
+    }
+}
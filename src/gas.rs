@@ -0,0 +1,138 @@
+// Gas budgeting for `donate`: matching a donation touches every matcher of the recipient, and if the transfer fails, `on_donate` has to roll every one of them back. So the gas that a donation needs grows with the number of matchers.
+// These are conservative estimates of each step's cost (see https://docs.near.org/concepts/basics/transactions/gas#the-cost-of-common-actions), rounded up to leave room for storage growth. `test_donation_to_a_full_recipient` in tests/integration-tests.rs checks them against the sandbox.
+// Since anyone can become a matcher, the number of matchers (and of matchers with eligibility checks) per recipient is capped, so that a donation always fits in a transaction.
+
+use crate::{Contract, ContractExt, MatcherAccountId, RecipientAccountId};
+use near_sdk::{env, near_bindgen, AccountId, Gas};
+
+const TGAS: u64 = 1_000_000_000_000;
+pub const GAS_FOR_DONATE: Gas = Gas(10 * TGAS); // `donate` (or `donate_many`) itself, excluding the per-recipient work below.
+pub const GAS_FOR_SENDING_DONATION: Gas = Gas(10 * TGAS); // Recording the donation, emitting its event, and scheduling the transfer and callback.
+pub const GAS_FOR_MATCHING_PER_MATCHER: Gas = Gas(3 * TGAS);
+pub const GAS_FOR_ON_DONATE: Gas = Gas(10 * TGAS); // `on_donate` without any matchers (refunding the donor, updating stats and the donation record).
+pub const GAS_FOR_ON_DONATE_PER_MATCHER: Gas = Gas(4 * TGAS); // Adding back one matcher's commitment and updating the leaderboards.
+pub const GAS_FOR_ON_RESCIND_MATCHING_FUNDS: Gas = Gas(10 * TGAS); // Adding a rescinded amount back to its commitment (and to the stats and indexes that go with it) if the transfer fails. The callback also gets a share of any unused gas.
+pub const GAS_FOR_ELIGIBILITY_CHECK: Gas = Gas(5 * TGAS); // The view method (such as `ft_balance_of`) that a matcher's eligibility check calls on another contract.
+pub const GAS_FOR_ON_ELIGIBLE_MATCH: Gas = Gas(10 * TGAS); // Rolling back one matcher's portion if its transfer fails.
+pub const GAS_FOR_ON_ELIGIBILITY_CHECK: Gas = Gas(15 * TGAS + GAS_FOR_ON_ELIGIBLE_MATCH.0); // Applying one matcher's portion and scheduling its transfer and callback.
+pub const MAX_GAS_PER_TRANSACTION: Gas = Gas(300 * TGAS);
+pub const MAX_MATCHERS_PER_RECIPIENT: u64 = 20;
+pub const MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT: u64 = 3; // Each one costs as much gas as several matchers. With every matcher and eligibility check (and an employer match), a donation needs 10 + 10 + 10 + 20 × (3 + 4) + 3 × (5 + 25) + (3 + 4) = 267 Tgas, which leaves room for a few matchers that a rollback re-adds beyond the cap.
+
+#[near_bindgen]
+impl Contract {
+    /// Returns how much gas to attach to `donate` so that a donation to `recipient` (by `donor`, if given, since their employer may match it too) can be matched and (if the transfer fails) rolled back.
+    pub fn estimate_donate_gas(&self, recipient: AccountId, donor: Option<AccountId>) -> Gas {
+        let gas_for_employer_match = match donor {
+            Some(donor) => self.gas_for_employer_match(&donor),
+            None => Gas(0),
+        };
+        GAS_FOR_DONATE + self.estimate_gas_for_sending_donation(&recipient) + gas_for_employer_match
+    }
+}
+
+impl Contract {
+    /// If `donor` is a registered employee, their employer's match costs as much gas as one more matcher.
+    pub(crate) fn gas_for_employer_match(&self, donor: &AccountId) -> Gas {
+        if self.is_registered_employee(donor) {
+            GAS_FOR_MATCHING_PER_MATCHER + GAS_FOR_ON_DONATE_PER_MATCHER
+        } else {
+            Gas(0)
+        }
+    }
+
+    pub(crate) fn gas_for_on_donate(matcher_count: u64) -> Gas {
+        GAS_FOR_ON_DONATE + Gas(GAS_FOR_ON_DONATE_PER_MATCHER.0 * matcher_count)
+    }
+
+    /// Returns how many matchers `recipient` has, and how many of them have an eligibility check.
+    fn count_matchers_and_eligibility_checks(&self, recipient: &RecipientAccountId) -> (u64, u64) {
+        self.recipients
+            .get(recipient)
+            .map(|matchers_for_this_recipient| {
+                let eligibility_check_count = matchers_for_this_recipient
+                    .keys()
+                    .filter(|matcher| self.has_eligibility_check(recipient, matcher))
+                    .count() as u64;
+                (matchers_for_this_recipient.len(), eligibility_check_count)
+            })
+            .unwrap_or((0, 0))
+    }
+
+    /// Whether `matcher` can commit to `recipient` without exceeding the caps above (which only apply to new matchers).
+    pub(crate) fn has_room_for_matcher(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) -> bool {
+        if self.get_commitment(recipient, matcher) > 0 {
+            return true;
+        }
+        let (matcher_count, eligibility_check_count) =
+            self.count_matchers_and_eligibility_checks(recipient);
+        matcher_count < MAX_MATCHERS_PER_RECIPIENT
+            && (!self.has_eligibility_check(recipient, matcher)
+                || eligibility_check_count < MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT)
+    }
+
+    pub(crate) fn assert_room_for_matcher(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) {
+        assert!(
+            self.has_room_for_matcher(recipient, matcher),
+            "{} already has the maximum number of matchers ({}, of which at most {} can have an eligibility check).",
+            recipient,
+            MAX_MATCHERS_PER_RECIPIENT,
+            MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT
+        );
+    }
+
+    /// Panics if setting an eligibility check for `matcher` would give `recipient` more matchers with checks than the cap allows.
+    pub(crate) fn assert_room_for_eligibility_check(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) {
+        if self.get_commitment(recipient, matcher) == 0
+            || self.has_eligibility_check(recipient, matcher)
+        {
+            return; // Checked by `assert_room_for_matcher` once they commit, or already counted.
+        }
+        let (_, eligibility_check_count) = self.count_matchers_and_eligibility_checks(recipient);
+        assert!(
+            eligibility_check_count < MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT,
+            "{} already has the maximum of {} matchers with eligibility checks.",
+            recipient,
+            MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT
+        );
+    }
+
+    /// The gas needed by `send_donation` (including its callbacks) for a donation to `recipient`, given its current matchers and their eligibility checks.
+    pub(crate) fn estimate_gas_for_sending_donation(&self, recipient: &RecipientAccountId) -> Gas {
+        let (matcher_count, eligibility_check_count) =
+            self.count_matchers_and_eligibility_checks(recipient);
+        GAS_FOR_SENDING_DONATION
+            + Gas(GAS_FOR_MATCHING_PER_MATCHER.0 * matcher_count)
+            + Self::gas_for_on_donate(matcher_count)
+            + Gas(
+                (GAS_FOR_ELIGIBILITY_CHECK.0 + GAS_FOR_ON_ELIGIBILITY_CHECK.0)
+                    * eligibility_check_count,
+            )
+    }
+
+    /// Panics (before any state changes) if the attached gas can't cover donating to all of `recipients` in the worst case.
+    pub(crate) fn assert_enough_gas_to_donate(&self, recipients: &[&RecipientAccountId]) {
+        let gas_for_employer_match = self.gas_for_employer_match(&env::signer_account_id());
+        let required_gas = recipients.iter().fold(GAS_FOR_DONATE, |gas, recipient| {
+            gas + self.estimate_gas_for_sending_donation(recipient) + gas_for_employer_match
+        });
+        assert!(
+            env::prepaid_gas() >= required_gas,
+            "Attach at least {} Tgas (only {} Tgas was attached). See estimate_donate_gas.",
+            (required_gas.0 + TGAS - 1) / TGAS, // Rounded up.
+            env::prepaid_gas().0 / TGAS
+        );
+    }
+}
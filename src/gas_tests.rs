@@ -0,0 +1,117 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod gas_tests {
+    use crate::gas::{
+        GAS_FOR_DONATE, GAS_FOR_MATCHING_PER_MATCHER, GAS_FOR_ON_DONATE,
+        GAS_FOR_ON_DONATE_PER_MATCHER, GAS_FOR_SENDING_DONATION,
+        MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT, MAX_GAS_PER_TRANSACTION, MAX_MATCHERS_PER_RECIPIENT,
+    };
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::set_context;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId, Gas};
+
+    fn matcher(index: u64) -> AccountId {
+        format!("matcher{}.near", index).parse().unwrap()
+    }
+
+    /// Commits 0.1 Ⓝ from `matcher(index)` to Alice, after setting an eligibility check if `with_check` is true.
+    fn offer_as(contract: &mut Contract, index: u64, with_check: bool) {
+        let amount = near_string_to_yocto(&"0.1".to_string());
+        testing_env!(VMContextBuilder::new()
+            .signer_account_id(matcher(index))
+            .attached_deposit(amount)
+            .build());
+        if with_check {
+            contract.set_eligibility_check(
+                accounts(0),
+                accounts(5),
+                "ft_balance_of".to_string(),
+                U128(1),
+            );
+        }
+        contract.offer_matching_funds(&accounts(0), None, None);
+    }
+
+    /// Fills Alice up to both caps.
+    fn fill_recipient(contract: &mut Contract) {
+        for index in 0..MAX_MATCHERS_PER_RECIPIENT {
+            offer_as(
+                contract,
+                index,
+                index < MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT,
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_donate_gas_grows_with_matchers() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.1".to_string());
+        let without_matchers = GAS_FOR_DONATE + GAS_FOR_SENDING_DONATION + GAS_FOR_ON_DONATE;
+        assert_eq!(
+            contract.estimate_donate_gas(recipient.clone(), None),
+            without_matchers
+        );
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, offer); // 2 = Charlie
+        contract.offer_matching_funds(&recipient, None, None);
+        assert_eq!(
+            contract.estimate_donate_gas(recipient, None),
+            without_matchers
+                + Gas(2 * (GAS_FOR_MATCHING_PER_MATCHER.0 + GAS_FOR_ON_DONATE_PER_MATCHER.0))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Attach at least 37 Tgas (only 30 Tgas was attached).")]
+    fn test_donate_with_too_little_gas() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let amount = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, amount); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        testing_env!(VMContextBuilder::new()
+            .signer_account_id(accounts(3)) // 3 = Danny
+            .attached_deposit(amount)
+            .prepaid_gas(Gas(30_000_000_000_000))
+            .build());
+        contract.donate(&recipient, None, None, None);
+    }
+
+    #[test]
+    fn test_donation_to_a_full_recipient_fits_in_a_transaction() {
+        let mut contract = Contract::new();
+        fill_recipient(&mut contract);
+        let gas_for_employer_match = GAS_FOR_MATCHING_PER_MATCHER + GAS_FOR_ON_DONATE_PER_MATCHER;
+        assert!(
+            contract.estimate_donate_gas(accounts(0), None) + gas_for_employer_match
+                <= MAX_GAS_PER_TRANSACTION
+        );
+        offer_as(&mut contract, 0, false); // Existing matchers can still add to their commitments.
+    }
+
+    #[test]
+    #[should_panic(expected = "alice already has the maximum number of matchers")]
+    fn test_matchers_per_recipient_are_capped() {
+        let mut contract = Contract::new();
+        fill_recipient(&mut contract);
+        offer_as(&mut contract, MAX_MATCHERS_PER_RECIPIENT, false);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "alice already has the maximum of 3 matchers with eligibility checks."
+    )]
+    fn test_eligibility_checks_per_recipient_are_capped() {
+        let mut contract = Contract::new();
+        fill_recipient(&mut contract);
+        offer_as(&mut contract, MAX_MATCHERS_PER_RECIPIENT - 1, true);
+    }
+}
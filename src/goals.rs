@@ -0,0 +1,382 @@
+// All-or-nothing fundraising goals: while a recipient's goal is active, donations to it (and the matching funds that they trigger) are held in this contract instead of being transferred. After the deadline, the held funds are released to the recipient if the goal was reached. Otherwise each donor can claim a refund and each matcher can claim back their commitment.
+
+use crate::events::{DonationRefundEventData, Event};
+use crate::generic::{self, did_promise_succeed, near_string_to_yocto, yocto_to_near_string};
+use crate::leaderboard::LeaderboardKind;
+use crate::{
+    Amount, Contract, ContractExt, InMemoryMatcherAmountMap, RecipientAccountId,
+    GAS_FOR_ACCOUNT_CALLBACK,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise};
+
+pub const MAX_GOAL_DURATION_MS: u64 = 365 * 24 * 60 * 60 * 1000; // 1 year, so that donors and matchers never have their funds held indefinitely.
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Goal {
+    pub number: u64, // Counts this recipient's goals, so that contributions to an earlier goal aren't mixed up with this one.
+    pub target_amount: Amount,
+    pub deadline_ms: u64,
+    pub donated: Amount, // Donations currently held for this goal.
+    pub matched: Amount, // Matching funds currently held for this goal.
+    pub released: bool,
+    pub refunding: Amount, // Refunds that are being transferred (and that are held for this goal again if their transfer fails).
+}
+
+/// What one account has contributed to a recipient's current goal (as a donor, as a matcher, or both).
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct GoalContribution {
+    pub donated: Amount,
+    pub public_donated: Amount, // The part of `donated` that was not anonymous (and so appears in the donor leaderboards).
+    pub matched: Amount,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GoalView {
+    pub target_amount: U128,
+    pub deadline_ms: u64,
+    pub donated: U128,
+    pub matched: U128,
+    pub status: String, // "active", "reached" (awaiting release), "released", or "failed".
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GoalContributionView {
+    pub donated: U128,
+    pub matched: U128,
+}
+
+impl Goal {
+    fn is_active(&self) -> bool {
+        env::block_timestamp_ms() < self.deadline_ms
+    }
+
+    fn was_reached(&self) -> bool {
+        self.donated + self.matched >= self.target_amount
+    }
+
+    fn status(&self) -> &'static str {
+        if self.released {
+            "released"
+        } else if self.is_active() {
+            "active"
+        } else if self.was_reached() {
+            "reached"
+        } else {
+            "failed"
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by a recipient to hold all donations (and their matches) until `deadline_ms` (at most `MAX_GOAL_DURATION_MS` from now), and to only receive them if they add up to at least `target_amount`.
+    /// target_amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn set_goal(&mut self, target_amount: generic::FormattedNearString, deadline_ms: u64) {
+        let recipient = env::signer_account_id();
+        let target_amount: Amount = near_string_to_yocto(&target_amount);
+        assert!(
+            target_amount > 0,
+            "The target amount must be greater than 0."
+        );
+        assert!(
+            deadline_ms > env::block_timestamp_ms(),
+            "The deadline must be in the future."
+        );
+        assert!(
+            deadline_ms - env::block_timestamp_ms() <= MAX_GOAL_DURATION_MS,
+            "The deadline can't be more than {} ms from now.",
+            MAX_GOAL_DURATION_MS
+        );
+        let mut number = 0;
+        if let Some(goal) = self.goals.get(&recipient) {
+            assert!(
+                goal.released
+                    || (!goal.is_active() && goal.donated + goal.matched + goal.refunding == 0),
+                "{} already has a goal whose funds haven't all been released or refunded.",
+                recipient
+            );
+            number = goal.number + 1;
+        }
+        assert!(
+            !self.has_unsettled_milestones(&recipient),
+            "A goal can't be combined with milestones whose funds haven't all been released or refunded."
+        );
+        self.goals.insert(
+            &recipient,
+            &Goal {
+                number,
+                target_amount,
+                deadline_ms,
+                donated: 0,
+                matched: 0,
+                released: false,
+                refunding: 0,
+            },
+        );
+    }
+
+    pub fn get_goal(&self, recipient: AccountId) -> Option<GoalView> {
+        self.goals.get(&recipient).map(|goal| GoalView {
+            target_amount: U128(goal.target_amount),
+            deadline_ms: goal.deadline_ms,
+            donated: U128(goal.donated),
+            matched: U128(goal.matched),
+            status: goal.status().to_string(),
+        })
+    }
+
+    pub fn get_goal_contribution(
+        &self,
+        recipient: AccountId,
+        account: AccountId,
+    ) -> GoalContributionView {
+        let contribution = self
+            .goals
+            .get(&recipient)
+            .and_then(|goal| {
+                self.goal_contributions
+                    .get(&(recipient, goal.number, account))
+            })
+            .unwrap_or_default();
+        GoalContributionView {
+            donated: U128(contribution.donated),
+            matched: U128(contribution.matched),
+        }
+    }
+
+    /// Anyone can call this after the deadline of a goal that was reached. The platform fee for the held donations is charged now.
+    pub fn release_goal_funds(&mut self, recipient: AccountId) -> Promise {
+        let mut goal = self
+            .goals
+            .get(&recipient)
+            .expect("This recipient has no goal.");
+        assert!(!goal.released, "These funds have already been released.");
+        assert!(
+            !goal.is_active(),
+            "The funds can't be released before the deadline."
+        );
+        assert!(
+            goal.was_reached(),
+            "The goal was not reached, so donors can claim refunds instead."
+        );
+        let amount = goal.donated + goal.matched;
+        let fee = self.calculate_fee(goal.donated, goal.matched);
+        self.pending_fees += fee;
+        self.total_held -= amount;
+        goal.released = true;
+        self.goals.insert(&recipient, &goal);
+        log!(
+            "Releasing {} (minus a fee of {}) to {}.",
+            yocto_to_near_string(&amount),
+            yocto_to_near_string(&fee),
+            recipient
+        );
+        self.transfer_from_escrow(&recipient, amount - fee).then(
+            Self::ext(env::current_account_id()) // escrow contract name
+                .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                .on_release_goal_funds(recipient, amount, fee),
+        )
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_release_goal_funds(&mut self, recipient: AccountId, amount: Amount, fee: Amount) {
+        let succeeded = did_promise_succeed();
+        self.settle_pending_fee(fee, succeeded);
+        if !succeeded {
+            // If transfer failed, hold the funds again so that releasing can be retried:
+            let mut goal = self.goals.get(&recipient).unwrap();
+            goal.released = false;
+            self.goals.insert(&recipient, &goal);
+            self.total_held += amount;
+        }
+    }
+
+    /// After the deadline of a goal that was not reached, refunds the caller's held donations and gives the caller's matching funds back to their commitment to `recipient`.
+    pub fn claim_goal_refund(&mut self, recipient: AccountId) {
+        let account = env::signer_account_id();
+        let mut goal = self
+            .goals
+            .get(&recipient)
+            .expect("This recipient has no goal.");
+        assert!(
+            !goal.is_active() && !goal.was_reached(),
+            "Refunds are only available after the deadline of a goal that was not reached."
+        );
+        let contribution = self
+            .goal_contributions
+            .remove(&(recipient.clone(), goal.number, account.clone()))
+            .expect("You have nothing to claim from this goal.");
+        goal.donated -= contribution.donated;
+        goal.matched -= contribution.matched;
+        goal.refunding += contribution.donated;
+        self.goals.insert(&recipient, &goal);
+        self.total_held -= contribution.donated + contribution.matched;
+        if contribution.matched > 0 {
+            self.add_to_commitment(&recipient, &account, contribution.matched);
+            self.total_escrowed += contribution.matched;
+            self.unrecord_matching(&recipient, contribution.matched);
+            self.subtract_from_leaderboards(
+                &recipient,
+                &account,
+                contribution.matched,
+                LeaderboardKind::Matchers,
+            );
+        }
+        if contribution.donated > 0 {
+            self.unrecord_donation(&recipient, &account, contribution.donated, 0);
+            self.subtract_from_leaderboards(
+                &recipient,
+                &account,
+                contribution.public_donated,
+                LeaderboardKind::Donors,
+            );
+            Event::DonationRefund(DonationRefundEventData {
+                recipient: recipient.clone(),
+                donor: if contribution.public_donated > 0 {
+                    Some(account.clone())
+                } else {
+                    None
+                },
+                amount: U128(contribution.donated),
+            })
+            .emit();
+            Promise::new(account.clone())
+                .transfer(contribution.donated) // Not using transfer_from_escrow because its log would reveal anonymous donors.
+                .then(
+                    Self::ext(env::current_account_id()) // escrow contract name
+                        .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                        .on_claim_goal_refund(
+                            recipient,
+                            goal.number,
+                            account,
+                            contribution.donated,
+                            contribution.public_donated,
+                        ),
+                );
+        }
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_claim_goal_refund(
+        &mut self,
+        recipient: AccountId,
+        goal_number: u64,
+        donor: AccountId,
+        donated: Amount,
+        public_donated: Amount,
+    ) {
+        // `set_goal` can't replace a goal while its refunds are being transferred, so this is still goal number `goal_number`:
+        let mut goal = self.goals.get(&recipient).unwrap();
+        goal.refunding -= donated;
+        if !did_promise_succeed() {
+            // If transfer failed, hold the donation again so that the donor can retry their claim:
+            goal.donated += donated;
+            self.total_held += donated;
+            self.hold_contribution(&recipient, goal_number, &donor, donated, public_donated, 0);
+            self.record_donation(&recipient, &donor, donated, 0);
+            self.add_to_leaderboards(&recipient, &donor, public_donated, LeaderboardKind::Donors);
+        }
+        self.goals.insert(&recipient, &goal);
+    }
+}
+
+impl Contract {
+    pub(crate) fn has_unreleased_goal(&self, recipient: &RecipientAccountId) -> bool {
+        self.goals
+            .get(recipient)
+            .map(|goal| {
+                !goal.released
+                    && (goal.is_active() || goal.donated + goal.matched + goal.refunding > 0)
+            })
+            .unwrap_or(false)
+    }
+
+    /// If `recipient` has an active goal, holds this donation and its matching funds for it (instead of them being transferred) and returns true.
+    pub(crate) fn hold_for_goal(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+        matched_amounts: &InMemoryMatcherAmountMap,
+        anonymous: bool,
+    ) -> bool {
+        let mut goal = match self.goals.get(recipient) {
+            Some(goal) if goal.is_active() => goal,
+            _ => return false,
+        };
+        let public_donated = if anonymous { 0 } else { donation_amount };
+        self.hold_contribution(
+            recipient,
+            goal.number,
+            donor,
+            donation_amount,
+            public_donated,
+            0,
+        );
+        let mut matched_amount: Amount = 0;
+        for (matcher, matched_amount_for_this_matcher) in matched_amounts.iter() {
+            matched_amount += matched_amount_for_this_matcher;
+            self.hold_contribution(
+                recipient,
+                goal.number,
+                matcher,
+                0,
+                0,
+                *matched_amount_for_this_matcher,
+            );
+        }
+        goal.donated += donation_amount;
+        goal.matched += matched_amount;
+        self.goals.insert(recipient, &goal);
+        self.total_held += donation_amount + matched_amount;
+        log!(
+            "Holding {} for the goal of {}.",
+            yocto_to_near_string(&(donation_amount + matched_amount)),
+            recipient
+        );
+        true
+    }
+
+    /// Takes the storage cost of a held donation (see send_donation) out of the donor's contribution, since it went to the reserve instead.
+    pub(crate) fn deduct_from_goal_contribution(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        amount: Amount,
+        anonymous: bool,
+    ) {
+        let mut goal = self.goals.get(recipient).expect("No goal found.");
+        let key = (recipient.clone(), goal.number, donor.clone());
+        let mut contribution = self.goal_contributions.get(&key).unwrap_or_default();
+        contribution.donated -= amount;
+        if !anonymous {
+            contribution.public_donated -= amount;
+        }
+        self.goal_contributions.insert(&key, &contribution);
+        goal.donated -= amount;
+        self.goals.insert(recipient, &goal);
+        self.total_held -= amount;
+    }
+
+    fn hold_contribution(
+        &mut self,
+        recipient: &RecipientAccountId,
+        goal_number: u64,
+        account: &AccountId,
+        donated: Amount,
+        public_donated: Amount,
+        matched: Amount,
+    ) {
+        let key = (recipient.clone(), goal_number, account.clone());
+        let mut contribution = self.goal_contributions.get(&key).unwrap_or_default();
+        contribution.donated += donated;
+        contribution.public_donated += public_donated;
+        contribution.matched += matched;
+        self.goal_contributions.insert(&key, &contribution);
+    }
+}
@@ -0,0 +1,169 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod goals_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::goals::{GoalContributionView, MAX_GOAL_DURATION_MS};
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context_at};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+
+    const NOW: u64 = 1_700_000_000_000;
+    const DEADLINE: u64 = NOW + 30 * 24 * 60 * 60 * 1000;
+
+    fn setup(target: &str) -> Contract {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        set_context_at(0, 0, NOW);
+        contract.set_goal(target.to_string(), DEADLINE);
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, near_string_to_yocto(&"0.1".to_string()), NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        contract
+    }
+
+    /// The part of Danny's donation that paid for its storage and so isn't held (see send_donation).
+    fn held_donation(contract: &Contract) -> u128 {
+        near_string_to_yocto(&"0.1".to_string())
+            - contract.get_donations(accounts(0), None, None)[0]
+                .storage_cost
+                .0
+    }
+
+    #[test]
+    fn test_reached_goal_is_released_after_deadline() {
+        let mut contract = setup("0.18"); // A little of the donation pays for its storage.
+        let recipient = accounts(0);
+        let matched = near_string_to_yocto(&"0.1".to_string());
+        let held = held_donation(&contract) + matched;
+        let goal = contract.get_goal(recipient.clone()).unwrap();
+        assert_eq!(goal.status, "active".to_string());
+        assert_eq!(goal.donated, U128(held_donation(&contract)));
+        assert_eq!(goal.matched, U128(matched));
+        assert_eq!(contract.check_invariants().total_held, U128(held));
+
+        set_context_at(5, 0, DEADLINE); // 5 = Fargo, anyone can release
+        assert_eq!(
+            contract.get_goal(recipient.clone()).unwrap().status,
+            "reached".to_string()
+        );
+        contract.release_goal_funds(recipient.clone());
+        assert_eq!(
+            contract.get_goal(recipient.clone()).unwrap().status,
+            "released".to_string()
+        );
+        assert_eq!(contract.check_invariants().total_held, U128(0));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_release_goal_funds(recipient.clone(), held, 0);
+        assert_eq!(contract.check_invariants().total_held, U128(held));
+        set_context_at(5, 0, DEADLINE);
+        contract.release_goal_funds(recipient); // Can be retried after a failed transfer.
+    }
+
+    #[test]
+    #[should_panic(expected = "The funds can't be released before the deadline.")]
+    fn test_goal_cannot_be_released_early() {
+        let mut contract = setup("0.2");
+        contract.release_goal_funds(accounts(0));
+    }
+
+    #[test]
+    fn test_failed_goal_refunds_donors_and_restores_matchers() {
+        let mut contract = setup("100");
+        let recipient = accounts(0);
+        let donation = held_donation(&contract);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+
+        set_context_at(3, 0, DEADLINE); // 3 = Danny
+        assert_eq!(
+            contract.get_goal(recipient.clone()).unwrap().status,
+            "failed".to_string()
+        );
+        contract.claim_goal_refund(recipient.clone());
+        assert_eq!(
+            contract.get_goal_contribution(recipient.clone(), accounts(3)),
+            GoalContributionView {
+                donated: U128(0),
+                matched: U128(0)
+            }
+        );
+        assert_eq!(
+            contract.get_stats(Some(recipient.clone())).total_donated,
+            U128(0)
+        );
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_claim_goal_refund(recipient.clone(), 0, accounts(3), donation, donation);
+        assert_eq!(
+            contract
+                .get_goal_contribution(recipient.clone(), accounts(3))
+                .donated,
+            U128(donation)
+        );
+
+        set_context_at(1, 0, DEADLINE); // 1 = Bob
+        contract.claim_goal_refund(recipient.clone());
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+        let report = contract.check_invariants();
+        assert_eq!(report.total_escrowed, report.sum_of_commitments);
+        assert_eq!(report.total_held, U128(donation));
+    }
+
+    #[test]
+    fn test_failed_refund_is_held_for_its_own_goal() {
+        let mut contract = setup("100");
+        let recipient = accounts(0);
+        let donation = held_donation(&contract);
+        set_context_at(1, 0, DEADLINE); // 1 = Bob
+        contract.claim_goal_refund(recipient.clone());
+        set_context_at(3, 0, DEADLINE); // 3 = Danny
+        contract.claim_goal_refund(recipient.clone());
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_claim_goal_refund(recipient.clone(), 0, accounts(3), donation, donation);
+        assert_eq!(
+            contract.get_goal(recipient.clone()).unwrap().donated,
+            U128(donation)
+        );
+        assert_eq!(
+            contract
+                .get_goal_contribution(recipient.clone(), accounts(3))
+                .donated,
+            U128(donation)
+        );
+        assert_eq!(contract.check_invariants().total_held, U128(donation));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "alice already has a goal whose funds haven't all been released or refunded."
+    )]
+    fn test_goal_cannot_be_replaced_while_a_refund_is_pending() {
+        let mut contract = setup("100");
+        let recipient = accounts(0);
+        set_context_at(1, 0, DEADLINE); // 1 = Bob
+        contract.claim_goal_refund(recipient.clone());
+        set_context_at(3, 0, DEADLINE); // 3 = Danny
+        contract.claim_goal_refund(recipient);
+
+        set_context_at(0, 0, DEADLINE); // 0 = Alice
+        contract.set_goal("1".to_string(), DEADLINE + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The deadline can't be more than 31536000000 ms from now.")]
+    fn test_goal_deadline_is_capped() {
+        let mut contract = Contract::new();
+        set_context_at(0, 0, NOW); // 0 = Alice
+        contract.set_goal("1".to_string(), NOW + MAX_GOAL_DURATION_MS + 1);
+    }
+}
@@ -5,6 +5,14 @@ pub mod generic {
 
     pub type FormattedNearString = String; // (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
 
+    #[allow(clippy::manual_range_contains, clippy::assign_op_pattern)] // In code generated by `construct_uint!`.
+    mod u256 {
+        uint::construct_uint! {
+            pub struct U256(4);
+        }
+    }
+    use u256::U256;
+
     const YOCTO_FACTOR: u128 = u128::pow(10, 24); // https://nomicon.io/Economics/Economic
     pub const DEFAULT_DECIMAL_PLACES: u32 = 4;
 
@@ -23,6 +31,21 @@ pub mod generic {
         env::sha256_array(account_id.as_bytes())
     }
 
+    /// The cost (in yoctoNEAR) of storing `bytes` more bytes in this contract's state.
+    pub(crate) fn storage_cost(bytes: usize) -> u128 {
+        bytes as u128 * env::storage_byte_cost()
+    }
+
+    /// The cost (in yoctoNEAR) of the storage that this contract has taken up since `env::storage_usage()` returned `storage_usage_before`, or 0 if it has freed storage since then.
+    pub(crate) fn storage_cost_since(storage_usage_before: u64) -> u128 {
+        storage_cost(env::storage_usage().saturating_sub(storage_usage_before) as usize)
+    }
+
+    /// `a * b / c` (rounded down) without overflowing on the multiplication. Used for splitting an amount in proportion to other amounts.
+    pub(crate) fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+        (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+    }
+
     /// Helper function to convert yoctoNEAR to $NEAR with _ decimals of precision.
     pub(crate) fn yocto_to_near(amount_in_yocto: &u128, decimal_places: u32) -> f64 {
         // TODO: Audit
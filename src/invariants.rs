@@ -0,0 +1,88 @@
+// Reconciles the money that this contract has taken into (or sent out of) escrow with the commitments recorded in `recipients`, so that bookkeeping bugs (e.g. after partially failed transfers) become visible.
+
+use crate::{Amount, Contract, ContractExt};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, near_bindgen};
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantReport {
+    pub total_escrowed: U128,
+    pub sum_of_commitments: U128,
+    pub outstanding_commitments: U128, // As tracked by the stats (see stats.rs).
+    pub accrued_fees: U128,
+    pub pending_fees: U128,
+    pub total_vault_balance: U128, // Funds deposited for recurring donations and matching top-ups (see vaults.rs).
+    pub total_held: U128, // Donations and matching funds held for goals and milestones (see goals.rs and milestones.rs).
+    pub account_balance: U128,
+    pub storage_locked: U128,
+    pub discrepancies: Vec<String>, // Empty if everything adds up.
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Compares `total_escrowed` with the sum of all commitments and with the part of this contract's balance that isn't locked for storage. All amounts in `discrepancies` are in yoctoNEAR.
+    pub fn check_invariants(&self) -> InvariantReport {
+        let mut discrepancies = Vec::new();
+        let mut sum_of_commitments: Amount = 0;
+        for recipient in self.committed_recipients.iter() {
+            let sum_for_this_recipient: Amount = self
+                .recipients
+                .get(&recipient)
+                .map(|matchers_for_this_recipient| matchers_for_this_recipient.values().sum())
+                .unwrap_or(0);
+            let outstanding_for_this_recipient = self
+                .recipient_stats
+                .get(&recipient)
+                .unwrap_or_default()
+                .outstanding_commitments;
+            if sum_for_this_recipient != outstanding_for_this_recipient {
+                discrepancies.push(format!(
+                    "The commitments to {} add up to {} but its stats say {}.",
+                    recipient, sum_for_this_recipient, outstanding_for_this_recipient
+                ));
+            }
+            sum_of_commitments += sum_for_this_recipient;
+        }
+        if self.total_escrowed != sum_of_commitments {
+            discrepancies.push(format!(
+                "total_escrowed is {} but all commitments add up to {}.",
+                self.total_escrowed, sum_of_commitments
+            ));
+        }
+        if self.contract_stats.outstanding_commitments != sum_of_commitments {
+            discrepancies.push(format!(
+                "The stats say that {} is committed but all commitments add up to {}.",
+                self.contract_stats.outstanding_commitments, sum_of_commitments
+            ));
+        }
+        let account_balance = env::account_balance();
+        let storage_locked = env::storage_usage() as Amount * env::storage_byte_cost();
+        let available_balance = account_balance.saturating_sub(storage_locked);
+        if available_balance
+            < self.total_escrowed
+                + self.accrued_fees
+                + self.pending_fees
+                + self.total_vault_balance
+                + self.total_held
+        {
+            discrepancies.push(format!(
+                "The balance minus storage ({}) does not cover total_escrowed ({}) plus accrued fees ({}) plus pending fees ({}) plus vaults ({}) plus held funds ({}).",
+                available_balance, self.total_escrowed, self.accrued_fees, self.pending_fees, self.total_vault_balance, self.total_held
+            ));
+        }
+        InvariantReport {
+            total_escrowed: U128(self.total_escrowed),
+            sum_of_commitments: U128(sum_of_commitments),
+            outstanding_commitments: U128(self.contract_stats.outstanding_commitments),
+            accrued_fees: U128(self.accrued_fees),
+            pending_fees: U128(self.pending_fees),
+            total_vault_balance: U128(self.total_vault_balance),
+            total_held: U128(self.total_held),
+            account_balance: U128(account_balance),
+            storage_locked: U128(storage_locked),
+            discrepancies,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod invariants_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_invariants_hold_after_offers_donations_rescinds_and_rollbacks() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, offer); // 2 = Charlie
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation), (accounts(2), donation)]),
+            0,
+            false,
+            0,
+        );
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+        set_context(1, true, near_string_to_yocto(&"100".to_string()), 0); // Must cover the storage that the mocked blockchain reports as used.
+
+        let report = contract.check_invariants();
+        assert_eq!(report.total_escrowed, U128(2 * offer - donation));
+        assert_eq!(report.sum_of_commitments, report.total_escrowed);
+        assert_eq!(report.discrepancies, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_invariants_report_discrepancies() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        contract.delete_all_matches_associated_with_recipient(recipient);
+        set_context(1, true, offer / 2, 0);
+
+        assert_eq!(
+            contract.check_invariants().discrepancies[0],
+            format!(
+                "total_escrowed is {} but all commitments add up to 0.",
+                offer
+            )
+        );
+        assert!(contract.check_invariants().discrepancies[1]
+            .starts_with("The balance minus storage (0) does not cover total_escrowed"));
+    }
+}
@@ -0,0 +1,141 @@
+// Top donors and top matchers, both per recipient and contract-wide.
+
+use crate::{Amount, Contract, ContractExt, RecipientAccountId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{near_bindgen, AccountId};
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// `None` means the contract-wide leaderboard; `Some(recipient)` means the leaderboard of that recipient.
+pub type LeaderboardScope = Option<RecipientAccountId>;
+
+#[derive(Clone, Copy)]
+pub enum LeaderboardKind {
+    Donors,
+    Matchers,
+}
+
+/// The top `LEADERBOARD_SIZE` accounts, sorted by amount (highest first).
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Leaderboard {
+    entries: Vec<(AccountId, Amount)>,
+}
+
+impl Leaderboard {
+    /// Sets the ranked total of `account`. Since only the top entries are kept, an account whose total decreases (e.g. after a failed transfer) keeps its spot until someone with a higher total displaces it.
+    fn update(&mut self, account: &AccountId, total: Amount) {
+        self.entries.retain(|(existing, _)| existing != account);
+        if total == 0 {
+            return;
+        }
+        let position = self
+            .entries
+            .iter()
+            .position(|(_, amount)| *amount < total)
+            .unwrap_or(self.entries.len());
+        if position < LEADERBOARD_SIZE {
+            self.entries.insert(position, (account.clone(), total));
+            self.entries.truncate(LEADERBOARD_SIZE);
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct Leaderboards {
+    top_donors: Leaderboard,
+    top_matchers: Leaderboard,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeaderboardEntry {
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the donors who have given the most to `recipient` (or across all recipients if no recipient is given). Anonymous donations are not counted.
+    pub fn get_top_donors(&self, recipient: Option<AccountId>) -> Vec<LeaderboardEntry> {
+        self.get_leaderboard(&recipient, LeaderboardKind::Donors)
+    }
+
+    /// Returns the matchers whose commitments have matched the most donations to `recipient` (or across all recipients if no recipient is given).
+    pub fn get_top_matchers(&self, recipient: Option<AccountId>) -> Vec<LeaderboardEntry> {
+        self.get_leaderboard(&recipient, LeaderboardKind::Matchers)
+    }
+}
+
+impl Contract {
+    fn get_leaderboard(
+        &self,
+        scope: &LeaderboardScope,
+        kind: LeaderboardKind,
+    ) -> Vec<LeaderboardEntry> {
+        let leaderboards = self.leaderboards.get(scope).unwrap_or_default();
+        let leaderboard = match kind {
+            LeaderboardKind::Donors => leaderboards.top_donors,
+            LeaderboardKind::Matchers => leaderboards.top_matchers,
+        };
+        leaderboard
+            .entries
+            .into_iter()
+            .map(|(account_id, amount)| LeaderboardEntry {
+                account_id,
+                amount: U128(amount),
+            })
+            .collect()
+    }
+
+    /// Adds `amount` to the ranked totals of `account` on both the leaderboard of `recipient` and the contract-wide leaderboard.
+    pub(crate) fn add_to_leaderboards(
+        &mut self,
+        recipient: &RecipientAccountId,
+        account: &AccountId,
+        amount: Amount,
+        kind: LeaderboardKind,
+    ) {
+        self.update_leaderboards(recipient, account, kind, |total| total + amount);
+    }
+
+    /// Reverses `add_to_leaderboards` after a failed transfer.
+    pub(crate) fn subtract_from_leaderboards(
+        &mut self,
+        recipient: &RecipientAccountId,
+        account: &AccountId,
+        amount: Amount,
+        kind: LeaderboardKind,
+    ) {
+        self.update_leaderboards(recipient, account, kind, |total| total - amount);
+    }
+
+    fn update_leaderboards(
+        &mut self,
+        recipient: &RecipientAccountId,
+        account: &AccountId,
+        kind: LeaderboardKind,
+        change: impl Fn(Amount) -> Amount,
+    ) {
+        for scope in [Some(recipient.clone()), None] {
+            let totals = match kind {
+                LeaderboardKind::Donors => &mut self.public_donor_totals,
+                LeaderboardKind::Matchers => &mut self.matcher_totals,
+            };
+            let key = (scope.clone(), account.clone());
+            let new_total = change(totals.get(&key).unwrap_or(0));
+            if new_total == 0 {
+                totals.remove(&key);
+            } else {
+                totals.insert(&key, &new_total);
+            }
+            let mut leaderboards = self.leaderboards.get(&scope).unwrap_or_default();
+            match kind {
+                LeaderboardKind::Donors => leaderboards.top_donors.update(account, new_total),
+                LeaderboardKind::Matchers => leaderboards.top_matchers.update(account, new_total),
+            }
+            self.leaderboards.insert(&scope, &leaderboards);
+        }
+    }
+}
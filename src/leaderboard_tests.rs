@@ -0,0 +1,91 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod leaderboard_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::leaderboard::LeaderboardEntry;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{AccountId, PromiseResult};
+    use std::collections::HashMap;
+
+    fn entry(account_id: AccountId, amount: u128) -> LeaderboardEntry {
+        LeaderboardEntry {
+            account_id,
+            amount: U128(amount),
+        }
+    }
+
+    #[test]
+    fn test_leaderboards_rank_by_total_and_skip_anonymous_donors() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"10".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let other_recipient = accounts(5); // 5 = Fargo
+        let one = near_string_to_yocto(&"1".to_string());
+        set_context(1, false, starting_balance, 5 * one); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 5 * one);
+        contract.offer_matching_funds(&other_recipient, None, None);
+
+        set_context(2, false, starting_balance, one); // 2 = Charlie
+        contract.donate(&recipient, None, None, None);
+        set_context(3, false, starting_balance, 2 * one); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        set_context(2, false, starting_balance, 2 * one);
+        contract.donate(&other_recipient, Some(false), None, None);
+        set_context(4, false, starting_balance, 3 * one); // 4 = Eugene
+        contract.donate(&recipient, Some(true), None, None);
+
+        assert_eq!(
+            contract.get_top_donors(Some(recipient.clone())),
+            vec![entry(accounts(3), 2 * one), entry(accounts(2), one)]
+        );
+        assert_eq!(
+            contract.get_top_donors(None),
+            vec![entry(accounts(2), 3 * one), entry(accounts(3), 2 * one)]
+        );
+        assert_eq!(
+            contract.get_top_matchers(Some(recipient)),
+            vec![entry(accounts(1), 5 * one)]
+        );
+        assert_eq!(
+            contract.get_top_matchers(None),
+            vec![entry(accounts(1), 7 * one)]
+        );
+        assert_eq!(contract.get_top_donors(Some(accounts(4))), vec![]);
+    }
+
+    #[test]
+    fn test_leaderboards_after_failed_donation() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"10".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let one = near_string_to_yocto(&"1".to_string());
+        set_context(1, false, starting_balance, 5 * one); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, one); // 2 = Charlie
+        contract.donate(&recipient, None, None, None);
+        set_context(3, false, starting_balance, 2 * one); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+
+        let matched_amounts = HashMap::from([(accounts(1), 2 * one)]);
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &(2 * one),
+            &matched_amounts,
+            0,
+            false,
+            1,
+        );
+
+        assert_eq!(contract.get_top_donors(None), vec![entry(accounts(2), one)]);
+        assert_eq!(
+            contract.get_top_matchers(Some(recipient)),
+            vec![entry(accounts(1), one)]
+        );
+    }
+}
@@ -1,21 +1,79 @@
 // Using https://github.com/near-examples/docs-examples/blob/4fda29c8cdabd9aba90787c553413db7725d88bd/donation-rs/contract/src/lib.rs as a basis
 
-use helpers::generic::{did_promise_succeed, hash_account_id, near_string_to_yocto};
+use donations::DonationRecord;
+use eligibility_checks::EligibilityCheck;
+use employers::{EmployeeMatchUsage, EmployerProgram};
+use events::{
+    CommitmentReallocatedEventData, CommitmentTransferredEventData, DonationEventData,
+    DonationRefundEventData, Event,
+};
+use fees::FeeConfig;
+use gas::GAS_FOR_ON_RESCIND_MATCHING_FUNDS;
+use goals::{Goal, GoalContribution};
+use helpers::generic::{
+    did_promise_succeed, hash_account_id, near_string_to_yocto, storage_cost_since,
+};
+use leaderboard::{LeaderboardKind, LeaderboardScope, Leaderboards};
+use locks::CommitmentLock;
+use matching_conditions::MatchingConditions;
+use milestones::{MilestoneContribution, MilestonePlan};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_sdk::collections::{LookupMap, LookupSet, TreeMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
 use near_sdk::{
-    env, log, near_bindgen, AccountId, Balance, BorshStorageKey, CryptoHash, Gas, PanicOnDefault,
-    Promise,
+    env, log, near_bindgen, AccountId, Balance, BorshStorageKey, CryptoHash, Gas, GasWeight,
+    PanicOnDefault, Promise,
 };
 use near_units::near;
+use rescind_requests::RescindRequest;
 use serde_json::{Map, Value};
+use stats::{ContractStats, RecipientStats};
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use subscriptions::Subscription;
+use top_ups::TopUpRule;
 use witgen::witgen;
 
+pub mod donations;
+pub mod donations_tests;
+pub mod eligibility_checks;
+pub mod eligibility_checks_tests;
+pub mod employers;
+pub mod employers_tests;
+pub mod events;
+pub mod events_tests;
+pub mod fees;
+pub mod fees_tests;
+pub mod gas;
+pub mod gas_tests;
+pub mod goals;
+pub mod goals_tests;
 mod helpers;
 pub mod helpers_tests;
+pub mod invariants;
+pub mod invariants_tests;
+pub mod leaderboard;
+pub mod leaderboard_tests;
 pub mod lib_tests;
+pub mod locks;
+pub mod locks_tests;
+pub mod matching_conditions;
+pub mod matching_conditions_tests;
+pub mod milestones;
+pub mod milestones_tests;
+pub mod rescind_requests;
+pub mod rescind_requests_tests;
+pub mod reserve;
+pub mod reserve_tests;
+pub mod stats;
+pub mod stats_tests;
+pub mod subscriptions;
+pub mod subscriptions_tests;
+pub mod top_ups;
+pub mod top_ups_tests;
+pub mod vaults;
+pub mod vaults_tests;
 use crate::generic::yocto_to_near_string;
 pub use crate::helpers::generic;
 
@@ -24,21 +82,117 @@ type Amount = Balance;
 type MatcherAccountId = AccountId;
 type MatcherAmountMap = UnorderedMap<MatcherAccountId, Amount>; // https://doc.rust-lang.org/reference/items/type-aliases.html
 type InMemoryMatcherAmountMap = HashMap<MatcherAccountId, Amount>;
+type InMemoryRecipientAmountMap = HashMap<RecipientAccountId, Amount>;
 type RecipientAccountId = AccountId;
 type MatcherAmountPerRecipient = LookupMap<RecipientAccountId, MatcherAmountMap>;
+type MatcherRecipientsIndex = LookupMap<MatcherAccountId, UnorderedSet<RecipientAccountId>>;
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CommitmentSummary {
+    pub recipient: RecipientAccountId,
+    pub amount_added: U128,
+    pub total_commitment: U128,
+}
 
-pub const GAS_FOR_ACCOUNT_CALLBACK: Gas = Gas(500_000_000_000); // gas for cross-contract calls, ~5 Tgas (teragas = 1e12) per "hop" // TODO: Document how to choose this number. https://docs.near.org/concepts/basics/transactions/gas#the-cost-of-common-actions
+/// What `rescind_matching_funds` and `execute_rescind` return. `promise_index` is the index of the `on_rescind_matching_funds` callback, whose result (true if the funds reached the matcher) can be read from the transaction's receipts.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RescindResult {
+    pub requested: U128,
+    pub rescinded: U128,
+    pub remaining: U128,
+    pub promise_index: u64,
+}
+
+pub const GAS_FOR_ACCOUNT_CALLBACK: Gas = Gas(500_000_000_000); // 0.5 Tgas (teragas = 1e12) of static gas for callbacks that roll back at most a single commitment per recipient (fee withdrawals, refunds, `rescind_all`). They are scheduled via `Self::ext`, which also gives them a share of the unused gas. `on_donate` and `on_rescind_matching_funds` instead get budgets from gas.rs. https://docs.near.org/concepts/basics/transactions/gas#the-cost-of-common-actions
 
 #[derive(BorshSerialize, BorshStorageKey)]
 enum StorageKey {
     Recipients,
     RecipientsInner { hash: CryptoHash },
+    RecipientStats,
+    DonorTotals,
+    RecipientDonorTotals,
+    MatcherRecipients,
+    MatcherRecipientsInner { hash: CryptoHash },
+    Leaderboards,
+    PublicDonorTotals,
+    MatcherTotals,
+    Donations,
+    DonationsInner { hash: CryptoHash },
+    PledgeMessages,
+    CommitmentTransferAuthorizations,
+    Locks,
+    RescindNoticePeriods,
+    RescindRequests,
+    CommittedRecipients,
+    Vaults,
+    Subscriptions,
+    TopUpRules,
+    Goals,
+    GoalContributions,
+    MilestonePlans,
+    MatchingConditions,
+    EligibilityChecks,
+    EmployerPrograms,
+    EmployersByEmployee,
+    EmployeeMatchUsage,
+    CommitmentNoticePeriods,
+    SubscriptionsByDonor,
+    SubscriptionsByDonorInner { hash: CryptoHash },
+    SubscriptionsByDueTime,
+    TopUpsByDueTime,
+    MilestoneContributions,
+    EmployerAcceptances,
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     pub recipients: MatcherAmountPerRecipient, // https://docs.near.org/concepts/storage/data-storage#unorderedmap The outer key-value pair is the "recipient: matcher-amount-map". The inner map (matcher amount) has a key-value pair of "matcher: amount".
+    pub matcher_recipients: MatcherRecipientsIndex, // The reverse of `recipients`: each matcher maps to the set of recipients they currently have funds committed to.
+    pub recipient_stats: LookupMap<RecipientAccountId, RecipientStats>,
+    pub contract_stats: ContractStats,
+    pub donor_totals: LookupMap<AccountId, Amount>, // Sum of all donations by each donor (across all recipients).
+    pub recipient_donor_totals: LookupMap<(RecipientAccountId, AccountId), Amount>, // Sum of all donations by each donor to each recipient.
+    pub leaderboards: LookupMap<LeaderboardScope, Leaderboards>,
+    pub public_donor_totals: LookupMap<(LeaderboardScope, AccountId), Amount>, // Like `donor_totals` and `recipient_donor_totals` but excluding anonymous donations. These are what the donor leaderboards rank.
+    pub matcher_totals: LookupMap<(LeaderboardScope, MatcherAccountId), Amount>, // Sum of all matching donations sent by each matcher.
+    pub donations: LookupMap<RecipientAccountId, Vector<DonationRecord>>,
+    pub pledge_messages: LookupMap<(RecipientAccountId, MatcherAccountId), String>, // Optional public message that a matcher attached to their commitment.
+    pub commitment_transfer_authorizations: LookupSet<(MatcherAccountId, MatcherAccountId)>, // Each entry is "new matcher, old matcher": the new matcher accepts commitments transferred from the old matcher.
+    pub locks: LookupMap<(RecipientAccountId, MatcherAccountId), CommitmentLock>,
+    pub default_rescind_notice_period_ms: u64, // Applies to every recipient that hasn't chosen its own notice period.
+    pub rescind_notice_periods: LookupMap<RecipientAccountId, u64>,
+    pub commitment_notice_periods: LookupMap<(RecipientAccountId, MatcherAccountId), u64>, // The notice period that applied when each commitment was made (see rescind_requests.rs).
+    pub rescind_requests: LookupMap<(RecipientAccountId, MatcherAccountId), RescindRequest>,
+    pub fee_config: FeeConfig,
+    pub accrued_fees: Amount, // Fees that have been charged but not yet withdrawn to the fee recipient.
+    pub pending_fees: Amount, // Fees charged on transfers that haven't completed yet. They move to `accrued_fees` once the transfer succeeds (see fees.rs).
+    pub reserve: Amount, // Funds that this contract owns (tips and storage costs paid by users). See reserve.rs.
+    pub total_escrowed: Amount, // Running total of matching funds received minus matching funds sent out (tracked independently of `recipients`; see invariants.rs).
+    pub committed_recipients: UnorderedSet<RecipientAccountId>, // Every recipient that currently has at least one commitment. Unlike `recipients`, this is iterable.
+    pub vaults: LookupMap<AccountId, Amount>, // Funds that accounts have deposited for recurring donations and matching top-ups. See vaults.rs.
+    pub total_vault_balance: Amount,
+    pub subscriptions: LookupMap<u64, Subscription>,
+    pub subscriptions_by_donor: LookupMap<AccountId, UnorderedSet<u64>>,
+    pub subscriptions_by_due_time: TreeMap<(u64, u64), ()>, // Keyed by (next_due_ms, subscription ID), so that due subscriptions can be found without scanning all of them.
+    pub next_subscription_id: u64,
+    pub top_up_rules: LookupMap<(RecipientAccountId, MatcherAccountId), TopUpRule>,
+    pub top_ups_by_due_time: TreeMap<(u64, RecipientAccountId, MatcherAccountId), ()>, // Keyed by the rule's next_due_ms first, so that due top-ups can be found without scanning all of the rules.
+    pub goals: LookupMap<RecipientAccountId, Goal>, // See goals.rs.
+    pub goal_contributions: LookupMap<(RecipientAccountId, u64, AccountId), GoalContribution>, // Keyed by recipient, goal number, and contributor.
+    pub milestone_plans: LookupMap<RecipientAccountId, MilestonePlan>, // See milestones.rs.
+    pub milestone_contributions:
+        LookupMap<(RecipientAccountId, u64, AccountId), MilestoneContribution>, // Keyed by recipient, plan number, and contributor.
+    pub total_held: Amount, // Donations and matching funds currently held for goals and milestones.
+    pub matching_conditions: LookupMap<(RecipientAccountId, MatcherAccountId), MatchingConditions>, // See matching_conditions.rs.
+    pub eligibility_checks: LookupMap<(RecipientAccountId, MatcherAccountId), EligibilityCheck>, // See eligibility_checks.rs.
+    pub employer_programs: LookupMap<AccountId, EmployerProgram>, // See employers.rs.
+    pub employers_by_employee: LookupMap<AccountId, AccountId>,
+    pub employee_match_usage: LookupMap<AccountId, EmployeeMatchUsage>,
+    pub employer_acceptances: LookupSet<(AccountId, AccountId)>, // Each entry is "employee, employer": the employee accepts being registered by the employer.
 }
 
 // ONEDAY: Review each part of this repo to ensure that it can scale to large amounts of data.
@@ -51,6 +205,49 @@ impl Contract {
         assert!(!env::state_exists(), "Already initialized");
         Self {
             recipients: MatcherAmountPerRecipient::new(StorageKey::Recipients),
+            matcher_recipients: MatcherRecipientsIndex::new(StorageKey::MatcherRecipients),
+            recipient_stats: LookupMap::new(StorageKey::RecipientStats),
+            contract_stats: ContractStats::default(),
+            donor_totals: LookupMap::new(StorageKey::DonorTotals),
+            recipient_donor_totals: LookupMap::new(StorageKey::RecipientDonorTotals),
+            leaderboards: LookupMap::new(StorageKey::Leaderboards),
+            public_donor_totals: LookupMap::new(StorageKey::PublicDonorTotals),
+            matcher_totals: LookupMap::new(StorageKey::MatcherTotals),
+            donations: LookupMap::new(StorageKey::Donations),
+            pledge_messages: LookupMap::new(StorageKey::PledgeMessages),
+            commitment_transfer_authorizations: LookupSet::new(
+                StorageKey::CommitmentTransferAuthorizations,
+            ),
+            locks: LookupMap::new(StorageKey::Locks),
+            default_rescind_notice_period_ms: 0,
+            rescind_notice_periods: LookupMap::new(StorageKey::RescindNoticePeriods),
+            commitment_notice_periods: LookupMap::new(StorageKey::CommitmentNoticePeriods),
+            rescind_requests: LookupMap::new(StorageKey::RescindRequests),
+            fee_config: FeeConfig::default(),
+            accrued_fees: 0,
+            pending_fees: 0,
+            reserve: 0,
+            total_escrowed: 0,
+            committed_recipients: UnorderedSet::new(StorageKey::CommittedRecipients),
+            vaults: LookupMap::new(StorageKey::Vaults),
+            total_vault_balance: 0,
+            subscriptions: LookupMap::new(StorageKey::Subscriptions),
+            subscriptions_by_donor: LookupMap::new(StorageKey::SubscriptionsByDonor),
+            subscriptions_by_due_time: TreeMap::new(StorageKey::SubscriptionsByDueTime),
+            next_subscription_id: 0,
+            top_up_rules: LookupMap::new(StorageKey::TopUpRules),
+            top_ups_by_due_time: TreeMap::new(StorageKey::TopUpsByDueTime),
+            goals: LookupMap::new(StorageKey::Goals),
+            goal_contributions: LookupMap::new(StorageKey::GoalContributions),
+            milestone_plans: LookupMap::new(StorageKey::MilestonePlans),
+            milestone_contributions: LookupMap::new(StorageKey::MilestoneContributions),
+            total_held: 0,
+            matching_conditions: LookupMap::new(StorageKey::MatchingConditions),
+            eligibility_checks: LookupMap::new(StorageKey::EligibilityChecks),
+            employer_programs: LookupMap::new(StorageKey::EmployerPrograms),
+            employers_by_employee: LookupMap::new(StorageKey::EmployersByEmployee),
+            employee_match_usage: LookupMap::new(StorageKey::EmployeeMatchUsage),
+            employer_acceptances: LookupSet::new(StorageKey::EmployerAcceptances),
         }
     }
 
@@ -67,6 +264,15 @@ impl Contract {
         self.recipients.get(recipient).expect(&msg)
     }
 
+    /// Like `get_expected_commitment` but returns 0 instead of panicking.
+    #[private]
+    fn get_commitment(&self, recipient: &RecipientAccountId, matcher: &AccountId) -> Amount {
+        self.recipients
+            .get(recipient)
+            .and_then(|matchers_for_this_recipient| matchers_for_this_recipient.get(matcher))
+            .unwrap_or(0)
+    }
+
     #[private]
     fn get_expected_commitment(
         &self,
@@ -88,39 +294,75 @@ impl Contract {
         existing_commitment
     }
 
-    #[payable] // Public - People can attach money
-    pub fn offer_matching_funds(&mut self, recipient: &AccountId) -> String {
-        let storage_cost: Amount = near_units::near::parse("0.001").unwrap(); // ONEDAY: Document how this value was decided.
-        let donation_amount: Amount = env::attached_deposit();
-        assert!(
-            donation_amount > storage_cost,
-            "Attach at least {} yoctoNEAR",
-            storage_cost
-        );
-        let matcher = env::signer_account_id(); // https://docs.near.org/develop/contracts/environment/
+    #[private]
+    fn get_offer_storage_cost() -> Amount {
+        near_units::near::parse("0.001").unwrap() // ONEDAY: Document how this value was decided.
+    }
 
+    /// Increases the commitment of `matcher` to `recipient` by `amount` and returns the updated commitment.
+    #[private]
+    fn add_to_commitment(
+        &mut self,
+        recipient: &AccountId,
+        matcher: &MatcherAccountId,
+        amount: Amount,
+    ) -> Amount {
         // Get the current map for the recipient. If it doesn't exist, create one.
         let mut matchers_for_this_recipient = self
             .recipients
-            .get(&recipient)
-            .unwrap_or_else(|| Self::create_new_matcher_amount_map(&recipient));
+            .get(recipient)
+            .unwrap_or_else(|| Self::create_new_matcher_amount_map(recipient));
 
         // If the matcher has already donated, increment their donation.
-        let existing_commitment = matchers_for_this_recipient.get(&matcher).unwrap_or(0);
+        let existing_commitment = matchers_for_this_recipient.get(matcher).unwrap_or(0);
         log!(
             "existing_commitment {}",
             yocto_to_near_string(&existing_commitment)
         );
-        let updated_commitment = donation_amount + existing_commitment;
+        let updated_commitment = amount + existing_commitment;
         log!(
             "updated_commitment {}",
             yocto_to_near_string(&updated_commitment)
         );
-        matchers_for_this_recipient.insert(&matcher, &updated_commitment);
+        matchers_for_this_recipient.insert(matcher, &updated_commitment);
         log!("inserted {}", &matcher);
 
         self.recipients
-            .insert(&recipient, &matchers_for_this_recipient);
+            .insert(recipient, &matchers_for_this_recipient);
+        self.record_commitment_change(recipient, matcher, existing_commitment, updated_commitment);
+        updated_commitment
+    }
+
+    /// `message` is an optional public pledge message (such as "We'll match every gift this week!") that gets shown by `get_commitment_details`. The cost of storing it is deducted from the commitment.
+    /// If `lock_until_ms` (a Unix timestamp in milliseconds) is provided, the funds committed by this call can't be rescinded before then, which donors can verify via `get_commitment_details`.
+    #[payable] // Public - People can attach money
+    pub fn offer_matching_funds(
+        &mut self,
+        recipient: &AccountId,
+        message: Option<String>,
+        lock_until_ms: Option<u64>,
+    ) -> String {
+        let storage_cost: Amount = Self::get_offer_storage_cost();
+        let message_storage_cost = Self::validate_memo(&message);
+        let attached_deposit: Amount = env::attached_deposit();
+        assert!(
+            attached_deposit > storage_cost + message_storage_cost,
+            "Attach at least {} yoctoNEAR",
+            storage_cost + message_storage_cost
+        );
+        let donation_amount: Amount = attached_deposit - message_storage_cost;
+        self.add_to_reserve(message_storage_cost);
+        let matcher = env::signer_account_id(); // https://docs.near.org/develop/contracts/environment/
+        self.assert_room_for_matcher(recipient, &matcher);
+        self.add_to_commitment(recipient, &matcher, donation_amount);
+        self.total_escrowed += donation_amount;
+        if let Some(message) = message {
+            self.pledge_messages
+                .insert(&(recipient.clone(), matcher.clone()), &message);
+        }
+        if let Some(lock_until_ms) = lock_until_ms {
+            self.add_lock(recipient, &matcher, donation_amount, lock_until_ms);
+        }
 
         let result = format!(
             "{} is now committed to match donations to {} up to a maximum of {}.",
@@ -132,6 +374,73 @@ impl Contract {
         result
     }
 
+    /// Panics unless `allocations` lists each recipient once with a positive amount and the amounts add up to `attached_deposit`.
+    #[private]
+    fn assert_allocations_add_up(allocations: &[(AccountId, U128)], attached_deposit: Amount) {
+        assert!(
+            !allocations.is_empty(),
+            "At least one allocation is required."
+        );
+        let mut sum_of_allocations: Amount = 0;
+        let mut seen_recipients = HashSet::new();
+        for (recipient, amount) in allocations.iter() {
+            assert!(
+                amount.0 > 0,
+                "The allocation to {} must be greater than 0.",
+                recipient
+            );
+            assert!(
+                seen_recipients.insert(recipient),
+                "{} is listed more than once.",
+                recipient
+            );
+            sum_of_allocations += amount.0;
+        }
+        assert_eq!(
+            sum_of_allocations,
+            attached_deposit,
+            "The allocations add up to {} but {} was attached.",
+            yocto_to_near_string(&sum_of_allocations),
+            yocto_to_near_string(&attached_deposit)
+        );
+    }
+
+    /// Splits the attached deposit into commitments to several recipients. `allocations` lists each recipient with the amount (in yoctoNEAR) to commit to them, and these amounts must add up to the attached deposit.
+    #[payable] // Public - People can attach money
+    pub fn offer_matching_funds_many(
+        &mut self,
+        allocations: Vec<(AccountId, U128)>,
+    ) -> Vec<CommitmentSummary> {
+        let storage_cost: Amount = Self::get_offer_storage_cost();
+        let attached_deposit: Amount = env::attached_deposit();
+        assert!(
+            attached_deposit > storage_cost,
+            "Attach at least {} yoctoNEAR",
+            storage_cost
+        );
+        Self::assert_allocations_add_up(&allocations, attached_deposit);
+        let matcher = env::signer_account_id();
+        let summaries: Vec<CommitmentSummary> = allocations
+            .into_iter()
+            .map(|(recipient, amount)| {
+                self.assert_room_for_matcher(&recipient, &matcher);
+                let total_commitment = self.add_to_commitment(&recipient, &matcher, amount.0);
+                self.total_escrowed += amount.0;
+                CommitmentSummary {
+                    recipient,
+                    amount_added: amount,
+                    total_commitment: U128(total_commitment),
+                }
+            })
+            .collect();
+        log!(
+            "{} is now committed to match donations to {} recipients.",
+            matcher,
+            summaries.len()
+        );
+        summaries
+    }
+
     pub fn get_commitments(&self, recipient: &AccountId) -> String {
         let mut map = Map::new();
         let matchers_for_this_recipient: MatcherAmountMap =
@@ -161,8 +470,55 @@ impl Contract {
         result
     }
 
+    /// Like `get_commitments`, but maps each matcher to an object with their commitment's "amount" plus (when they apply) their pledge "message", their "locked_amount" and "locked_until_ms", and their "pending_rescind_amount" and "pending_rescind_available_at_ms".
+    pub fn get_commitment_details(&self, recipient: &AccountId) -> String {
+        let mut map = Map::new();
+        let matchers_for_this_recipient: MatcherAmountMap =
+            self.get_expected_matchers_for_this_recipient(recipient);
+        let matchers = matchers_for_this_recipient.keys_as_vector();
+        for matcher in matchers.iter() {
+            let existing_commitment =
+                self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
+            let mut details = Map::new();
+            details.insert(
+                "amount".to_string(),
+                Value::String(yocto_to_near_string(&existing_commitment)),
+            );
+            if let Some(message) = self
+                .pledge_messages
+                .get(&(recipient.clone(), matcher.clone()))
+            {
+                details.insert("message".to_string(), Value::String(message));
+            }
+            let locked_amount = self.get_locked_amount(recipient, &matcher);
+            if locked_amount > 0 {
+                let lock = self
+                    .locks
+                    .get(&(recipient.clone(), matcher.clone()))
+                    .unwrap();
+                details.insert(
+                    "locked_amount".to_string(),
+                    Value::String(yocto_to_near_string(&locked_amount)),
+                );
+                details.insert("locked_until_ms".to_string(), Value::from(lock.until_ms));
+            }
+            if let Some(request) = self.get_rescind_request(recipient, &matcher) {
+                details.insert(
+                    "pending_rescind_amount".to_string(),
+                    Value::String(yocto_to_near_string(&request.amount)),
+                );
+                details.insert(
+                    "pending_rescind_available_at_ms".to_string(),
+                    Value::from(request.available_at_ms),
+                );
+            }
+            map.insert(matcher.to_string(), Value::Object(details));
+        }
+        Value::Object(map).to_string()
+    }
+
     pub fn transfer_from_escrow(&self, destination_account: &AccountId, amount: Amount) -> Promise {
-        // ONEDAY: Consider subtracting storage cost like https://github.com/near-examples/docs-examples/blob/4fda29c8cdabd9aba90787c553413db7725d88bd/donation-rs/contract/src/lib.rs#L51
+        // Storage is paid for out of the reserve (tips and the storage costs charged for memos and pledge messages; see reserve.rs) rather than by deducting from transfers like https://github.com/near-examples/docs-examples/blob/4fda29c8cdabd9aba90787c553413db7725d88bd/donation-rs/contract/src/lib.rs#L51
         log!(
             "transfer_from_escrow destination_account: {}, amount: {}",
             destination_account,
@@ -172,7 +528,7 @@ impl Contract {
     }
 
     /**
-     * Gets called via `rescind_matching_funds` and `send_matching_donation`.
+     * Gets called via `rescind_matching_funds`, `send_matching_donation`, and `on_eligibility_check`.
      */
     #[private]
     fn set_matcher_amount(
@@ -190,6 +546,7 @@ impl Contract {
         // ONEDAY assert_self(); assert_single_promise_success();
         let mut matchers_for_this_recipient =
             self.get_expected_matchers_for_this_recipient(recipient);
+        let previous_amount = matchers_for_this_recipient.get(matcher).unwrap_or(0);
         if amount == 0 {
             log!("set_matcher_amount removing matcher {}", &matcher);
             matchers_for_this_recipient.remove(matcher);
@@ -200,6 +557,7 @@ impl Contract {
         log!("len = {}", matchers_for_this_recipient.len());
         self.recipients
             .insert(&recipient, &matchers_for_this_recipient);
+        self.record_commitment_change(recipient, matcher, previous_amount, amount);
         matchers_for_this_recipient
     }
 
@@ -208,30 +566,60 @@ impl Contract {
         &mut self,
         recipient: &AccountId,
         matcher: AccountId,
-        original_amount: Amount,
+        rescinded_amount: Amount,
+    ) -> bool {
+        let succeeded = did_promise_succeed();
+        if !succeeded {
+            // If transfer failed, add back what was rescinded. Anything else that happened to this commitment in the meantime (e.g. matching a donation, or its inner map getting deleted) is preserved.
+            self.total_escrowed += rescinded_amount;
+            self.add_to_commitment(recipient, &matcher, rescinded_amount);
+        }
+        succeeded
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_rescind_all(
+        &mut self,
+        matcher: AccountId,
+        rescinded_amounts: InMemoryRecipientAmountMap,
     ) {
         if !did_promise_succeed() {
-            // If transfer failed, change the state back to what it was:
-            self.set_matcher_amount(recipient, &matcher, original_amount);
+            // If transfer failed, add back what was rescinded from each recipient (on top of any commitments that the matcher made in the meantime):
+            for (recipient, rescinded_amount) in rescinded_amounts.iter() {
+                self.total_escrowed += rescinded_amount;
+                self.add_to_commitment(recipient, &matcher, *rescinded_amount);
+            }
         }
     }
 
     /// requested_withdrawal_amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    /// Requests larger than the commitment rescind the whole commitment, unless `strict` is true, in which case they fail.
     pub fn rescind_matching_funds(
         &mut self,
         recipient: &AccountId,
         requested_withdrawal_amount: generic::FormattedNearString,
-    ) -> String {
+        strict: Option<bool>,
+    ) -> RescindResult {
         let matcher = env::signer_account_id();
+        self.assert_no_rescind_notice_period(recipient, &matcher);
         let matchers_for_this_recipient = self.get_expected_matchers_for_this_recipient(recipient);
         let amount_already_committed =
             self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
         let requested_withdrawal_amount_yocto: Amount =
             near_string_to_yocto(&requested_withdrawal_amount);
-        let result;
+        if strict.unwrap_or(false) {
+            assert!(
+                requested_withdrawal_amount_yocto <= amount_already_committed,
+                "{} only has {} committed to {}.",
+                matcher,
+                yocto_to_near_string(&amount_already_committed),
+                recipient
+            );
+        }
         let amount_to_decrease =
             cmp::min(requested_withdrawal_amount_yocto, amount_already_committed);
         let new_amount = amount_already_committed - amount_to_decrease;
+        self.assert_not_below_locked_amount(recipient, &matcher, new_amount);
         let end_of_msg = if new_amount > 0 {
             format!(
                 "will only be committed to match donations to {} up to a maximum of {}",
@@ -241,40 +629,272 @@ impl Contract {
         } else {
             format!("will no longer be matching donations to {}", recipient,)
         };
-        result = format!(
+        log!(
             "{} is about to rescind {} and then {}.",
             &matcher,
             yocto_to_near_string(&amount_to_decrease),
             end_of_msg
         );
-        log!(result);
         self.set_matcher_amount(recipient, &matcher, new_amount);
-        self.transfer_from_escrow(&matcher, amount_to_decrease) // Funds go from escrow back to the matcher.
+        let promise_index = self.send_rescinded_funds(recipient, &matcher, amount_to_decrease);
+        RescindResult {
+            requested: U128(requested_withdrawal_amount_yocto),
+            rescinded: U128(amount_to_decrease),
+            remaining: U128(new_amount),
+            promise_index,
+        }
+    }
+
+    /// Sends rescinded funds from escrow back to the matcher, with `on_rescind_matching_funds` as the callback. This uses the low-level promise API (rather than `transfer_from_escrow`) so that the index of the callback can be returned to the caller.
+    #[private]
+    fn send_rescinded_funds(
+        &mut self,
+        recipient: &AccountId,
+        matcher: &AccountId,
+        amount: Amount,
+    ) -> u64 {
+        log!(
+            "send_rescinded_funds destination_account: {}, amount: {}",
+            matcher,
+            yocto_to_near_string(&amount)
+        );
+        self.total_escrowed -= amount;
+        let transfer_index = env::promise_batch_create(matcher);
+        env::promise_batch_action_transfer(transfer_index, amount);
+        #[derive(Serialize)]
+        #[serde(crate = "near_sdk::serde")]
+        struct CallbackArgs<'a> {
+            recipient: &'a AccountId,
+            matcher: &'a AccountId,
+            rescinded_amount: Amount, // `serde_json::Value` can't hold a u128, so this struct gets serialized directly.
+        }
+        let callback_args = serde_json::to_vec(&CallbackArgs {
+            recipient,
+            matcher,
+            rescinded_amount: amount,
+        })
+        .expect("Could not serialize callback arguments");
+        let callback_index = env::promise_batch_then(transfer_index, &env::current_account_id());
+        env::promise_batch_action_function_call_weight(
+            callback_index,
+            "on_rescind_matching_funds",
+            &callback_args,
+            0,
+            GAS_FOR_ON_RESCIND_MATCHING_FUNDS,
+            GasWeight(1), // Like `Self::ext`, so that the callback also gets a share of the unused gas.
+        );
+        callback_index
+    }
+
+    /// Rescinds every commitment of the caller (to every recipient) and sends all of those funds back in a single transfer.
+    pub fn rescind_all(&mut self) -> String {
+        let matcher = env::signer_account_id();
+        let recipients_for_this_matcher = self
+            .matcher_recipients
+            .get(&matcher)
+            .unwrap_or_else(|| panic!("{} does not currently have any funds committed.", matcher))
+            .to_vec();
+        let mut rescinded_amounts = InMemoryRecipientAmountMap::new();
+        let mut amount_to_rescind: Amount = 0;
+        for recipient in recipients_for_this_matcher {
+            let matchers_for_this_recipient =
+                self.get_expected_matchers_for_this_recipient(&recipient);
+            let amount_already_committed =
+                self.get_expected_commitment(&recipient, &matchers_for_this_recipient, &matcher);
+            self.assert_no_rescind_notice_period(&recipient, &matcher);
+            self.assert_not_below_locked_amount(&recipient, &matcher, 0);
+            self.set_matcher_amount(&recipient, &matcher, 0);
+            amount_to_rescind += amount_already_committed;
+            rescinded_amounts.insert(recipient, amount_already_committed);
+        }
+        let result = format!(
+            "{} is about to rescind {} and then will no longer be matching donations to any of these {} recipients.",
+            &matcher,
+            yocto_to_near_string(&amount_to_rescind),
+            rescinded_amounts.len()
+        );
+        log!(result);
+        self.total_escrowed -= amount_to_rescind;
+        self.transfer_from_escrow(&matcher, amount_to_rescind) // Funds go from escrow back to the matcher.
             .then(
                 Self::ext(env::current_account_id()) // escrow contract name
                     .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
-                    .on_rescind_matching_funds(recipient, matcher, amount_already_committed),
+                    .on_rescind_all(matcher, rescinded_amounts),
             );
         result
     }
 
-    // Only gets called internally by send_matching_donations.
+    /// Moves part (or all) of the caller's commitment from one recipient to another. The funds stay in escrow, so no transfer happens.
+    /// amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn reallocate_commitment(
+        &mut self,
+        from_recipient: &AccountId,
+        to_recipient: &AccountId,
+        amount: generic::FormattedNearString,
+    ) -> String {
+        assert_ne!(
+            from_recipient, to_recipient,
+            "The commitment must be moved to a different recipient."
+        );
+        let matcher = env::signer_account_id();
+        self.assert_no_rescind_notice_period(from_recipient, &matcher);
+        let matchers_for_this_recipient =
+            self.get_expected_matchers_for_this_recipient(from_recipient);
+        let amount_already_committed =
+            self.get_expected_commitment(from_recipient, &matchers_for_this_recipient, &matcher);
+        let amount_yocto: Amount = near_string_to_yocto(&amount);
+        assert!(amount_yocto > 0, "The amount must be greater than 0.");
+        assert!(
+            amount_yocto <= amount_already_committed,
+            "{} only has {} committed to {}.",
+            matcher,
+            yocto_to_near_string(&amount_already_committed),
+            from_recipient
+        );
+        self.assert_not_below_locked_amount(
+            from_recipient,
+            &matcher,
+            amount_already_committed - amount_yocto,
+        );
+        self.set_matcher_amount(
+            from_recipient,
+            &matcher,
+            amount_already_committed - amount_yocto,
+        );
+        self.assert_room_for_matcher(to_recipient, &matcher);
+        self.add_to_commitment(to_recipient, &matcher, amount_yocto);
+        Event::CommitmentReallocated(CommitmentReallocatedEventData {
+            matcher: matcher.clone(),
+            from_recipient: from_recipient.clone(),
+            to_recipient: to_recipient.clone(),
+            amount: U128(amount_yocto),
+        })
+        .emit();
+        let result = format!(
+            "{} moved {} of their commitment from {} to {}.",
+            matcher,
+            yocto_to_near_string(&amount_yocto),
+            from_recipient,
+            to_recipient
+        );
+        log!(result);
+        result
+    }
+
+    /// Allows `matcher` to transfer their commitments to the caller via `transfer_commitment`. Nobody can take over another matcher's commitment without accepting it first.
+    pub fn accept_commitment_transfers_from(&mut self, matcher: AccountId) {
+        let new_matcher = env::signer_account_id();
+        log!(
+            "{} now accepts commitment transfers from {}.",
+            &new_matcher,
+            &matcher
+        );
+        self.commitment_transfer_authorizations
+            .insert(&(new_matcher, matcher));
+    }
+
+    pub fn revoke_commitment_transfers_from(&mut self, matcher: AccountId) {
+        let new_matcher = env::signer_account_id();
+        log!(
+            "{} no longer accepts commitment transfers from {}.",
+            &new_matcher,
+            &matcher
+        );
+        self.commitment_transfer_authorizations
+            .remove(&(new_matcher, matcher));
+    }
+
+    /// Moves part (or all) of the caller's commitment to `recipient` into `new_matcher`'s commitment to the same recipient. `new_matcher` must have called `accept_commitment_transfers_from` first.
+    /// Like `reallocate_commitment`, this is only allowed without a notice period (see rescind_requests.rs), since it would otherwise be a way around it.
+    /// amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn transfer_commitment(
+        &mut self,
+        recipient: &AccountId,
+        new_matcher: AccountId,
+        amount: generic::FormattedNearString,
+    ) -> String {
+        let matcher = env::signer_account_id();
+        assert_ne!(
+            matcher, new_matcher,
+            "The commitment must be transferred to a different matcher."
+        );
+        assert!(
+            self.commitment_transfer_authorizations
+                .contains(&(new_matcher.clone(), matcher.clone())),
+            "{} has not accepted commitment transfers from {}.",
+            new_matcher,
+            matcher
+        );
+        self.assert_no_rescind_notice_period(recipient, &matcher);
+        let matchers_for_this_recipient = self.get_expected_matchers_for_this_recipient(recipient);
+        let amount_already_committed =
+            self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
+        let amount_yocto: Amount = near_string_to_yocto(&amount);
+        assert!(amount_yocto > 0, "The amount must be greater than 0.");
+        assert!(
+            amount_yocto <= amount_already_committed,
+            "{} only has {} committed to {}.",
+            matcher,
+            yocto_to_near_string(&amount_already_committed),
+            recipient
+        );
+        self.assert_not_below_locked_amount(
+            recipient,
+            &matcher,
+            amount_already_committed - amount_yocto,
+        );
+        self.set_matcher_amount(recipient, &matcher, amount_already_committed - amount_yocto);
+        self.assert_room_for_matcher(recipient, &new_matcher);
+        self.add_to_commitment(recipient, &new_matcher, amount_yocto);
+        Event::CommitmentTransferred(CommitmentTransferredEventData {
+            recipient: recipient.clone(),
+            from_matcher: matcher.clone(),
+            to_matcher: new_matcher.clone(),
+            amount: U128(amount_yocto),
+        })
+        .emit();
+        let result = format!(
+            "{} transferred {} of their commitment to {} to {}.",
+            matcher,
+            yocto_to_near_string(&amount_yocto),
+            recipient,
+            new_matcher
+        );
+        log!(result);
+        result
+    }
+
+    // Only gets called internally by send_matching_donations. Matchers with an eligibility check are skipped and returned separately, since they can only be matched after their check (see eligibility_checks.rs).
     #[private]
     fn record_matching_donations_as_sent(
         &mut self,
         donation_amount: &Amount,
         recipient: &AccountId,
-    ) -> (Amount, InMemoryMatcherAmountMap) {
+        donor: &AccountId,
+        anonymous: bool,
+    ) -> (Amount, InMemoryMatcherAmountMap, Vec<MatcherAccountId>) {
         let mut sum_of_donations_to_send = *donation_amount;
         let mut matchers_for_this_recipient: MatcherAmountMap =
             self.get_expected_matchers_for_this_recipient(&recipient);
-        let mut original_commitments = InMemoryMatcherAmountMap::new();
+        let mut matched_amounts = InMemoryMatcherAmountMap::new();
+        let mut matchers_to_check = Vec::new();
         let mut matcher_keys = Vec::new();
         for matcher in matchers_for_this_recipient.keys() {
             // ONEDAY: What is a more elegant way of writing this function?
             matcher_keys.push(matcher);
         }
         for matcher in matcher_keys {
+            if !self.meets_matching_conditions(recipient, &matcher, donor, *donation_amount) {
+                log!(
+                    "This donation doesn't meet the matching conditions of {}.",
+                    &matcher
+                );
+                continue;
+            }
+            if self.has_eligibility_check(recipient, &matcher) {
+                matchers_to_check.push(matcher);
+                continue;
+            }
             let existing_commitment =
                 self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
             let matched_amount: u128 = cmp::min(*donation_amount, existing_commitment);
@@ -299,7 +919,19 @@ impl Contract {
                 matchers_for_this_recipient.insert(&matcher, &remaining_commitment);
                 log!("inserted {}", &matcher);
             }
-            original_commitments.insert(matcher, existing_commitment);
+            self.record_commitment_change(
+                recipient,
+                &matcher,
+                existing_commitment,
+                remaining_commitment,
+            );
+            self.add_to_leaderboards(
+                recipient,
+                &matcher,
+                matched_amount,
+                LeaderboardKind::Matchers,
+            );
+            matched_amounts.insert(matcher, matched_amount);
             sum_of_donations_to_send += matched_amount;
         }
         log!(
@@ -308,50 +940,269 @@ impl Contract {
         );
         self.recipients
             .insert(&recipient, &matchers_for_this_recipient);
-        (sum_of_donations_to_send, original_commitments)
+        self.record_donation(
+            recipient,
+            donor,
+            *donation_amount,
+            sum_of_donations_to_send - donation_amount,
+        );
+        if !anonymous {
+            self.add_to_leaderboards(recipient, donor, *donation_amount, LeaderboardKind::Donors);
+        }
+        (sum_of_donations_to_send, matched_amounts, matchers_to_check)
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[private] // Public - but only callable by env::current_account_id()
     pub fn on_donate(
         &mut self,
-        _donation_amount: &Amount,
-        _original_commitments: &InMemoryMatcherAmountMap,
+        recipient: &AccountId,
+        donor: AccountId,
+        donation_amount: &Amount,
+        matched_amounts: &InMemoryMatcherAmountMap,
+        fee: Amount,
+        anonymous: bool,
+        donation_index: u64,
     ) {
-        if !did_promise_succeed() {
-            // If transfer failed, change the state back to what it was:
-            unimplemented!(); // TODO If transfer failed, change the state back to what it was (and send funds back to donor?), doing it for every matcher of this recipient
+        let succeeded = did_promise_succeed();
+        self.settle_pending_fee(fee, succeeded);
+        if !succeeded {
+            // If transfer failed, give every matcher of this recipient back what was taken from their commitment and send the donation back to the donor.
+            // (Adding back, rather than restoring a snapshot, preserves any changes that the matchers made to their commitments while the transfer was in flight.)
+            let mut matched_amount: Amount = 0;
+            for (matcher, matched_amount_for_this_matcher) in matched_amounts.iter() {
+                matched_amount += matched_amount_for_this_matcher;
+                self.add_to_commitment(recipient, matcher, *matched_amount_for_this_matcher);
+                self.subtract_from_leaderboards(
+                    recipient,
+                    matcher,
+                    *matched_amount_for_this_matcher,
+                    LeaderboardKind::Matchers,
+                );
+            }
+            self.total_escrowed += matched_amount;
+            self.unrecord_donation(recipient, &donor, *donation_amount, matched_amount);
+            if let Some((employer, employer_matched_amount)) =
+                self.get_donation_employer_match(recipient, donation_index)
+            {
+                self.unapply_employer_match(
+                    recipient,
+                    &donor,
+                    &employer,
+                    employer_matched_amount,
+                    anonymous,
+                );
+            }
+            if !anonymous {
+                self.subtract_from_leaderboards(
+                    recipient,
+                    &donor,
+                    *donation_amount,
+                    LeaderboardKind::Donors,
+                );
+            }
+            log!(
+                "Transfer to {} failed. Refunding {} to the donor.",
+                recipient,
+                yocto_to_near_string(donation_amount)
+            );
+            Event::DonationRefund(DonationRefundEventData {
+                recipient: recipient.clone(),
+                donor: if anonymous { None } else { Some(donor.clone()) },
+                amount: U128(*donation_amount),
+            })
+            .emit();
+            let tip = self.get_donation_tip(recipient, donation_index);
+            let storage_cost = self.get_donation_storage_cost(recipient, donation_index);
+            self.subtract_from_reserve(tip); // The tip was meant to accompany the donation, so it gets refunded too.
+            self.mark_donation_refunded(recipient, donation_index);
+            Promise::new(donor).transfer(*donation_amount - storage_cost + tip);
+            // The record stays (marked as refunded), so its storage cost isn't refunded. Not using transfer_from_escrow because its log would reveal anonymous donors.
         }
     }
 
+    /// If `anonymous` is true, the donor's account won't appear in this contract's logs, events, or leaderboards. (It is still recorded internally so that a failed donation can be refunded.)
+    /// `memo` is an optional short message (such as a dedication) stored with the donation.
+    /// `tip` is an optional amount (in yoctoNEAR, deducted from the attached deposit) that goes to this contract's reserve rather than to the recipient.
+    /// The cost of storing the donation (its record, memo, and any new stats or leaderboard entries) is deducted from the donation.
     #[payable] // Public - People can attach money
-    pub fn donate(&mut self, recipient: &AccountId) {
-        let donation_amount: Amount = env::attached_deposit();
-        assert!(donation_amount > 0, "Attaching some yoctoNEAR is required.");
-        let prepaid_gas = env::prepaid_gas();
-        let gas_already_burned = env::used_gas();
-        let gas_to_be_burned_during_transfer_from_escrow = GAS_FOR_ACCOUNT_CALLBACK;
-        let remaining_gas =
-            prepaid_gas - gas_already_burned - gas_to_be_burned_during_transfer_from_escrow;
-        log!(
-            "prepaid_gas={:?}, gas_already_burned={:?}, gas_to_be_burned_during_transfer_from_escrow={:?}, remaining_gas={:?}",
-            prepaid_gas,
-            gas_already_burned,
-            gas_to_be_burned_during_transfer_from_escrow,
-            remaining_gas
-          );
-        let (sum_of_donations_to_send, original_commitments) =
-            self.record_matching_donations_as_sent(&donation_amount, &recipient); // Optimistically change state.
-        self.transfer_from_escrow(&recipient, sum_of_donations_to_send) // Then do the actual transfer. The donor attached a deposit which this contract owns at this point. Immediately pass it along to the intended recipient along with all matching funds.
+    pub fn donate(
+        &mut self,
+        recipient: &AccountId,
+        anonymous: Option<bool>,
+        memo: Option<String>,
+        tip: Option<U128>,
+    ) {
+        self.assert_enough_gas_to_donate(&[recipient]);
+        Self::validate_memo(&memo); // Its storage is measured along with the rest of the donation's (see send_donation).
+        let tip = tip.map(|tip| tip.0).unwrap_or(0);
+        let attached_deposit: Amount = env::attached_deposit();
+        assert!(
+            attached_deposit > tip,
+            "Attaching more than {} yoctoNEAR is required.",
+            tip
+        );
+        let donation_amount: Amount = attached_deposit - tip;
+        self.add_to_reserve(tip);
+        self.send_donation(
+            recipient,
+            env::signer_account_id(),
+            donation_amount,
+            anonymous.unwrap_or(false),
+            memo,
+            tip,
+        );
+    }
+
+    /// Splits the attached deposit across several recipients. `allocations` lists each recipient with the amount (in yoctoNEAR) that they should receive, and these amounts must add up to the attached deposit.
+    /// Each recipient's donation is matched and transferred separately (with its own rollback callback), so a failed transfer to one recipient doesn't affect the others.
+    #[payable] // Public - People can attach money
+    pub fn donate_many(&mut self, allocations: Vec<(AccountId, U128)>, anonymous: Option<bool>) {
+        let attached_deposit: Amount = env::attached_deposit();
+        Self::assert_allocations_add_up(&allocations, attached_deposit);
+        self.assert_enough_gas_to_donate(
+            &allocations
+                .iter()
+                .map(|(recipient, _)| recipient)
+                .collect::<Vec<_>>(),
+        );
+        let donor = env::signer_account_id();
+        let anonymous = anonymous.unwrap_or(false);
+        for (recipient, amount) in allocations.iter() {
+            self.send_donation(recipient, donor.clone(), amount.0, anonymous, None, 0);
+        }
+    }
+
+    /// Matches `donation_amount` (which the donor has already attached), records the donation, and transfers the donation plus all matching funds (minus the fee and the donation's storage cost) to `recipient` (or holds them if `recipient` has an active goal or milestones left to approve; see goals.rs and milestones.rs).
+    #[private]
+    fn send_donation(
+        &mut self,
+        recipient: &AccountId,
+        donor: AccountId,
+        donation_amount: Amount,
+        anonymous: bool,
+        memo: Option<String>,
+        tip: Amount,
+    ) {
+        let storage_usage_before = env::storage_usage();
+        let (mut sum_of_donations_to_send, matched_amounts, matchers_to_check) =
+            self.record_matching_donations_as_sent(&donation_amount, &recipient, &donor, anonymous); // Optimistically change state.
+        self.total_escrowed -= sum_of_donations_to_send - donation_amount;
+        let held = self.hold_for_goal(
+            recipient,
+            &donor,
+            donation_amount,
+            &matched_amounts,
+            anonymous,
+        );
+        let held_for_milestones = !held
+            && self.hold_for_milestones(
+                recipient,
+                &donor,
+                donation_amount,
+                &matched_amounts,
+                anonymous,
+            );
+        let employer_match = if held || held_for_milestones {
+            None // A failed goal (or expired milestones) would have no commitment to give an employer's match back to, so held donations aren't matched by employers.
+        } else {
+            self.apply_employer_match(recipient, &donor, donation_amount, anonymous)
+        };
+        if let Some((_, employer_matched_amount)) = &employer_match {
+            sum_of_donations_to_send += employer_matched_amount;
+        }
+        let matched_amount = sum_of_donations_to_send - donation_amount;
+        let fee = if held || held_for_milestones {
+            0 // Charged when the goal's funds are released (or a milestone is approved).
+        } else {
+            self.calculate_fee(donation_amount, matched_amount)
+        };
+        let donation_index = self.add_donation_record(
+            recipient,
+            &DonationRecord {
+                donor: donor.clone(),
+                anonymous,
+                amount: donation_amount,
+                matched_amount,
+                fee,
+                tip,
+                employer_match: employer_match.clone(),
+                memo: memo.clone(),
+                timestamp_ms: env::block_timestamp_ms(),
+                refunded: false,
+                storage_cost: 0,
+            },
+        );
+        // The donor pays for everything this donation added to storage (its record and memo, and any new stats, leaderboard, or contribution entries).
+        let storage_cost = storage_cost_since(storage_usage_before);
+        assert!(
+            donation_amount > storage_cost,
+            "Donating more than {} yoctoNEAR is required to cover storage.",
+            storage_cost
+        );
+        self.add_to_reserve(storage_cost);
+        self.set_donation_storage_cost(recipient, donation_index, storage_cost);
+        if held {
+            self.deduct_from_goal_contribution(recipient, &donor, storage_cost, anonymous);
+        } else if held_for_milestones {
+            self.deduct_from_milestone_contribution(recipient, &donor, storage_cost, anonymous);
+        }
+        if held || held_for_milestones {
+            // Held donations are counted as only what is held for the recipient (without their storage cost), so that a goal refund takes them out of the stats entirely.
+            self.unrecord_donation(recipient, &donor, storage_cost, 0);
+            if !anonymous {
+                self.subtract_from_leaderboards(
+                    recipient,
+                    &donor,
+                    storage_cost,
+                    LeaderboardKind::Donors,
+                );
+            }
+        }
+        Event::Donation(DonationEventData {
+            recipient: recipient.clone(),
+            donor: if anonymous { None } else { Some(donor.clone()) },
+            amount: U128(donation_amount),
+            matched_amount: U128(matched_amount),
+            fee: if fee > 0 { Some(U128(fee)) } else { None },
+            tip: if tip > 0 { Some(U128(tip)) } else { None },
+            memo,
+        })
+        .emit();
+        self.check_eligibility(
+            recipient,
+            matchers_to_check,
+            &donor,
+            donation_amount,
+            anonymous,
+            donation_index,
+        );
+        if held || held_for_milestones {
+            return;
+        }
+        self.pending_fees += fee;
+        self.transfer_from_escrow(&recipient, sum_of_donations_to_send - fee - storage_cost) // Then do the actual transfer. The donor attached a deposit which this contract owns at this point. Immediately pass it along to the intended recipient along with all matching funds (minus the platform fee, which stays in this contract).
             .then(
                 Self::ext(env::current_account_id()) // escrow contract name
-                    .with_static_gas(gas_to_be_burned_during_transfer_from_escrow)
-                    .on_donate(&donation_amount, &original_commitments), //In the callback, undo the state change if the transfer failed.
+                    .with_static_gas(Self::gas_for_on_donate(
+                        matched_amounts.len() as u64 + u64::from(employer_match.is_some()),
+                    )) // Rolling back takes more gas the more matchers there are.
+                    .on_donate(
+                        recipient,
+                        donor,
+                        &donation_amount,
+                        &matched_amounts,
+                        fee,
+                        anonymous,
+                        donation_index,
+                    ), //In the callback, undo the state change if the transfer failed.
             );
     }
 
     #[private] // Public - but only callable by env::current_account_id()
     pub fn delete_all_matches_associated_with_recipient(&mut self, recipient: AccountId) {
         // Since self.recipients is a LookupMap (not iterable), there is no clear() function available for instantly deleting all keys.
+        // The funds behind the deleted commitments stay in this contract (and in total_escrowed), so check_invariants will report them.
         // ONEDAY assert_self();
         let mut matchers_for_this_recipient: MatcherAmountMap =
             self.get_expected_matchers_for_this_recipient(&recipient);
@@ -362,8 +1213,9 @@ impl Contract {
         }
         for key in to_remove.iter() {
             // https://stackoverflow.com/a/45724774/470749
-            matchers_for_this_recipient.remove(key); // If not for this loop, the contract state would be messed up, and we would later get "The collection is an inconsistent state" errors.
+            let removed_amount = matchers_for_this_recipient.remove(key).unwrap_or(0); // If not for this loop, the contract state would be messed up, and we would later get "The collection is an inconsistent state" errors.
             log!("Removed {} from {}", &key, &recipient);
+            self.record_commitment_change(&recipient, key, removed_amount, 0);
         }
         self.recipients.remove(&recipient); // See comment above about why removing each inner map is also necessary.
     }
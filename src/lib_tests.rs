@@ -1,14 +1,16 @@
 // https://www.near-sdk.io/testing/unit-tests
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
-mod lib_tests {
+pub(crate) mod lib_tests {
     use crate::generic::{near_string_to_yocto, yocto_to_near_string};
-    use crate::{Amount, Contract};
+    use crate::{Amount, CommitmentSummary, Contract};
 
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{env, log, testing_env, Balance};
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::{env, log, testing_env, Balance, PromiseResult, RuntimeFeesConfig, VMConfig};
+    use std::collections::HashMap;
 
-    fn set_context(
+    pub(crate) fn set_context(
         account_index: usize,
         is_view: bool,
         starting_balance: Balance,
@@ -23,6 +25,31 @@ mod lib_tests {
         testing_env!(context);
     }
 
+    /// Like `set_context` but at a specific block time (in milliseconds since the Unix epoch).
+    pub(crate) fn set_context_at(account_index: usize, deposit: Amount, block_timestamp_ms: u64) {
+        let context = VMContextBuilder::new()
+            .signer_account_id(accounts(account_index))
+            .attached_deposit(deposit)
+            .block_timestamp(block_timestamp_ms * 1_000_000)
+            .build();
+        testing_env!(context);
+    }
+
+    /// Simulates this contract receiving the result of the promise that a callback was attached to.
+    pub(crate) fn set_callback_context(promise_result: PromiseResult) {
+        let context = VMContextBuilder::new()
+            .predecessor_account_id(accounts(0))
+            .current_account_id(accounts(0))
+            .build();
+        testing_env!(
+            context,
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
     fn log_balance() {
         log!(
             "account_balance: {:?}: {:?}",
@@ -50,7 +77,7 @@ mod lib_tests {
             near_string_to_yocto(&"0.3".to_string()),
         );
         log_balance();
-        let _matcher1_offer_result = contract.offer_matching_funds(&recipient);
+        let _matcher1_offer_result = contract.offer_matching_funds(&recipient, None, None);
         log_balance();
         set_context(
             2, // 2 = Charlie
@@ -58,7 +85,7 @@ mod lib_tests {
             starting_balance,
             near_string_to_yocto(&"0.1".to_string()),
         );
-        let _matcher2_offer_result = contract.offer_matching_funds(&recipient);
+        let _matcher2_offer_result = contract.offer_matching_funds(&recipient, None, None);
         // Unit tests cannot assert that this (escrow) contract now contains the correct amount of funds. The integration tests should do that and also assert that the matchers' account balances have decreased appropriately.
         log_balance();
         let result = contract.get_commitments(&recipient);
@@ -68,7 +95,7 @@ mod lib_tests {
         );
         set_context(1, false, starting_balance, 0);
         let _matcher1_rescind_result =
-            contract.rescind_matching_funds(&recipient, "0.02 Ⓝ".to_string());
+            contract.rescind_matching_funds(&recipient, "0.02 Ⓝ".to_string(), None);
         // Unit tests cannot assert funds received via transfer (check state). The integration tests should.
         let result_after_rescind = contract.get_commitments(&recipient);
         assert_eq!(
@@ -102,7 +129,7 @@ mod lib_tests {
             near_string_to_yocto(&"0.1".to_string()),
         );
         log_balance();
-        let _matcher1_offer_result = contract.offer_matching_funds(&recipient);
+        let _matcher1_offer_result = contract.offer_matching_funds(&recipient, None, None);
         log_balance();
         set_context(
             1, // 1 = Bob
@@ -110,25 +137,69 @@ mod lib_tests {
             starting_balance,
             near_string_to_yocto(&"0.1".to_string()),
         );
-        let _matcher2_offer_result = contract.offer_matching_funds(&recipient);
+        let _matcher2_offer_result = contract.offer_matching_funds(&recipient, None, None);
         // Unit tests cannot assert that this (escrow) contract now contains the correct amount of funds. The integration tests should do that and also assert that the matchers' account balances have decreased appropriately.
         log_balance();
         let result = contract.get_commitments(&recipient);
         assert_eq!(result, "{\"bob\":\"0.2 Ⓝ\"}".to_string());
         set_context(1, false, starting_balance, 0);
         let _matcher1_rescind_result1 =
-            contract.rescind_matching_funds(&recipient, "0.02 Ⓝ".to_string());
+            contract.rescind_matching_funds(&recipient, "0.02 Ⓝ".to_string(), None);
         // Unit tests cannot assert funds received via transfer (check state). The integration tests should.
         let result_after_rescind1 = contract.get_commitments(&recipient);
         assert_eq!(result_after_rescind1, "{\"bob\":\"0.18 Ⓝ\"}".to_string());
         log!("Someone trying to rescind more than their remaining commitment...");
-        let _matcher1_rescind_result2 =
-            contract.rescind_matching_funds(&recipient, "99 Ⓝ".to_string());
+        let matcher1_rescind_result2 =
+            contract.rescind_matching_funds(&recipient, "99 Ⓝ".to_string(), None);
+        assert_eq!(
+            matcher1_rescind_result2.requested,
+            U128(near_string_to_yocto(&"99".to_string()))
+        );
+        assert_eq!(
+            matcher1_rescind_result2.rescinded,
+            U128(near_string_to_yocto(&"0.18".to_string()))
+        );
+        assert_eq!(matcher1_rescind_result2.remaining, U128(0));
         // Unit tests cannot assert funds received via transfer (check state). The integration tests should.
         let result_after_rescind2 = contract.get_commitments(&recipient);
         assert_eq!(result_after_rescind2, "{}".to_string());
     }
 
+    #[test]
+    #[should_panic(expected = "bob only has 0.1 Ⓝ committed to alice.")]
+    fn test_strict_rescind_beyond_commitment() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        set_context(
+            1,
+            false,
+            starting_balance,
+            near_string_to_yocto(&"0.1".to_string()),
+        ); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.2 Ⓝ".to_string(), Some(true));
+    }
+
+    #[test]
+    fn test_on_rescind_matching_funds_reports_failure() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let offer = near_string_to_yocto(&"0.1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), Some(true));
+        set_callback_context(PromiseResult::Failed);
+        assert!(!contract.on_rescind_matching_funds(&recipient, accounts(1), offer));
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+    }
+
     #[test]
     fn test_offer_matching_funds_and_donate_and_get_commitments() {
         let mut contract = Contract::new();
@@ -149,12 +220,12 @@ mod lib_tests {
             offer,
         );
         log_balance();
-        let _matcher1_offer_result = contract.offer_matching_funds(&recipient);
+        let _matcher1_offer_result = contract.offer_matching_funds(&recipient, None, None);
 
         log_balance();
 
         set_context(2, false, starting_balance, donation);
-        let _donate_result = contract.donate(&recipient);
+        let _donate_result = contract.donate(&recipient, None, None, None);
         // Unit tests cannot assert funds received via transfer (check state). The integration tests should.
         let commitments_after_donate = contract.get_commitments(&recipient);
         assert_eq!(
@@ -165,4 +236,381 @@ mod lib_tests {
             )
         );
     }
+
+    #[test]
+    fn test_donate_many() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient1 = accounts(0); // 0 = Alice
+        let recipient2 = accounts(4); // 4 = Eugene
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation1 = near_string_to_yocto(&"0.2".to_string());
+        let donation2 = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient1, None, None);
+        set_context(2, false, starting_balance, offer); // 2 = Charlie
+        contract.offer_matching_funds(&recipient2, None, None);
+
+        set_context(3, false, starting_balance, donation1 + donation2); // 3 = Danny
+        contract.donate_many(
+            vec![
+                (recipient1.clone(), U128(donation1)),
+                (recipient2.clone(), U128(donation2)),
+            ],
+            None,
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"charlie\":\"0.2 Ⓝ\"}".to_string()
+        );
+
+        // Only the failed leg gets rolled back:
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient2,
+            accounts(3),
+            &donation2,
+            &HashMap::from([(accounts(2), donation2)]),
+            0,
+            false,
+            0,
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"charlie\":\"0.3 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.get_stats(None).total_donated, U128(donation1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The allocations add up to 0.3 Ⓝ but 0.4 Ⓝ was attached.")]
+    fn test_donate_many_with_wrong_total() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient1 = accounts(0); // 0 = Alice
+        let recipient2 = accounts(4); // 4 = Eugene
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(3, false, starting_balance, 4 * donation); // 3 = Danny
+        contract.donate_many(
+            vec![
+                (recipient1, U128(2 * donation)),
+                (recipient2, U128(donation)),
+            ],
+            None,
+        );
+    }
+
+    #[test]
+    fn test_offer_matching_funds_many() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient1 = accounts(0); // 0 = Alice
+        let recipient2 = accounts(4); // 4 = Eugene
+        let offer1 = near_string_to_yocto(&"0.3".to_string());
+        let offer2 = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer2); // 1 = Bob
+        contract.offer_matching_funds(&recipient2, None, None);
+
+        set_context(1, false, starting_balance, offer1 + offer2);
+        let summaries = contract.offer_matching_funds_many(vec![
+            (recipient1.clone(), U128(offer1)),
+            (recipient2.clone(), U128(offer2)),
+        ]);
+        assert_eq!(
+            summaries,
+            vec![
+                CommitmentSummary {
+                    recipient: recipient1.clone(),
+                    amount_added: U128(offer1),
+                    total_commitment: U128(offer1),
+                },
+                CommitmentSummary {
+                    recipient: recipient2.clone(),
+                    amount_added: U128(offer2),
+                    total_commitment: U128(2 * offer2),
+                }
+            ]
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "alice is listed more than once.")]
+    fn test_offer_matching_funds_many_with_duplicate_recipient() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let offer = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, 2 * offer); // 1 = Bob
+        contract.offer_matching_funds_many(vec![
+            (accounts(0), U128(offer)),
+            (accounts(0), U128(offer)),
+        ]);
+    }
+
+    #[test]
+    fn test_reallocate_commitment() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient1 = accounts(0); // 0 = Alice
+        let recipient2 = accounts(4); // 4 = Eugene
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient1, None, None);
+
+        set_context(1, false, starting_balance, 0);
+        contract.reallocate_commitment(&recipient1, &recipient2, "0.1 Ⓝ".to_string());
+        assert_eq!(
+            get_logs()
+                .into_iter()
+                .filter(|log| log.starts_with("EVENT_JSON:"))
+                .collect::<Vec<String>>(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"donation_matcher","version":"1.0.0","event":"commitment_reallocated","data":{{"matcher":"bob","from_recipient":"alice","to_recipient":"eugene","amount":"{}"}}}}"#,
+                near_string_to_yocto(&"0.1".to_string())
+            )]
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+
+        contract.reallocate_commitment(&recipient1, &recipient2, "0.2 Ⓝ".to_string());
+        assert_eq!(contract.get_commitments(&recipient1), "{}".to_string());
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_stats(None).outstanding_commitments,
+            U128(offer)
+        );
+        assert_eq!(contract.get_stats(None).active_matchers, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "bob only has 0.3 Ⓝ committed to alice.")]
+    fn test_reallocate_commitment_beyond_commitment() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&accounts(0), None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.reallocate_commitment(&accounts(0), &accounts(4), "0.4 Ⓝ".to_string());
+    }
+
+    #[test]
+    fn test_transfer_commitment() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, 0); // 2 = Charlie
+        contract.accept_commitment_transfers_from(accounts(1));
+
+        set_context(1, false, starting_balance, 0);
+        contract.transfer_commitment(&recipient, accounts(2), "0.1 Ⓝ".to_string());
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.2 Ⓝ\",\"charlie\":\"0.1 Ⓝ\"}".to_string()
+        );
+
+        contract.transfer_commitment(&recipient, accounts(2), "0.2 Ⓝ".to_string());
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"charlie\":\"0.3 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.get_stats(None).active_matchers, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "charlie has not accepted commitment transfers from bob.")]
+    fn test_transfer_commitment_without_acceptance() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, 0); // 2 = Charlie
+        contract.accept_commitment_transfers_from(accounts(1));
+        contract.revoke_commitment_transfers_from(accounts(1));
+
+        set_context(1, false, starting_balance, 0);
+        contract.transfer_commitment(&recipient, accounts(2), "0.1 Ⓝ".to_string());
+    }
+
+    #[test]
+    fn test_rescind_all() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient1 = accounts(0); // 0 = Alice
+        let recipient2 = accounts(4); // 4 = Eugene
+        let offer1 = near_string_to_yocto(&"0.3".to_string());
+        let offer2 = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer1 + offer2); // 1 = Bob
+        contract.offer_matching_funds_many(vec![
+            (recipient1.clone(), U128(offer1)),
+            (recipient2.clone(), U128(offer2)),
+        ]);
+        set_context(2, false, starting_balance, offer2); // 2 = Charlie
+        contract.offer_matching_funds(&recipient1, None, None);
+
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_all();
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"charlie\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.get_commitments(&recipient2), "{}".to_string());
+        assert_eq!(contract.get_stats(None).active_matchers, 1);
+
+        // If the transfer fails, every commitment is restored:
+        set_callback_context(PromiseResult::Failed);
+        contract.on_rescind_all(
+            accounts(1),
+            HashMap::from([(recipient1.clone(), offer1), (recipient2.clone(), offer2)]),
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient1),
+            "{\"bob\":\"0.3 Ⓝ\",\"charlie\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient2),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.get_stats(None).active_matchers, 2);
+    }
+
+    #[test]
+    fn test_failed_donation_after_rescind_only_adds_back_matched_amount() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+
+        // Bob rescinds the rest of his commitment before the donation's callback runs:
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.2 Ⓝ".to_string(), None);
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            false,
+            0,
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.check_invariants().total_escrowed, U128(donation));
+    }
+
+    #[test]
+    fn test_failed_rescind_all_after_new_offer_keeps_new_offer() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer1 = near_string_to_yocto(&"0.3".to_string());
+        let offer2 = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer1); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_all();
+
+        // Bob offers again before the rescind's callback runs:
+        set_context(1, false, starting_balance, offer2);
+        contract.offer_matching_funds(&recipient, None, None);
+        set_callback_context(PromiseResult::Failed);
+        contract.on_rescind_all(accounts(1), HashMap::from([(recipient.clone(), offer1)]));
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.4 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.check_invariants().total_escrowed,
+            U128(offer1 + offer2)
+        );
+    }
+
+    #[test]
+    fn test_failed_rescind_after_matched_donation_keeps_donation() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let rescind = near_string_to_yocto(&"0.1".to_string());
+        let donation = near_string_to_yocto(&"0.05".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+
+        // A donation gets matched and Bob rescinds again before the first rescind's callback runs:
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.05 Ⓝ".to_string(), None);
+        set_callback_context(PromiseResult::Failed);
+        assert!(!contract.on_rescind_matching_funds(&recipient, accounts(1), rescind));
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.check_invariants().discrepancies,
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_failed_rescind_recreates_deleted_inner_map() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let rescind = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+        contract.delete_all_matches_associated_with_recipient(recipient.clone());
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_rescind_matching_funds(&recipient, accounts(1), rescind);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+        assert_eq!(contract.get_stats(None).active_matchers, 1);
+    }
 }
@@ -0,0 +1,89 @@
+// Locks let a matcher make a credible pledge: until the lock expires, they can't rescind (or move) the locked part of their commitment.
+
+use crate::generic::yocto_to_near_string;
+use crate::{Amount, Contract, MatcherAccountId, RecipientAccountId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CommitmentLock {
+    pub amount: Amount,
+    pub until_ms: u64,
+}
+
+impl Contract {
+    /// Locks `amount` more of the commitment of `matcher` to `recipient` until `until_ms` (a Unix timestamp in milliseconds). If part of the commitment is already locked, the whole locked amount stays locked until the later of the two times.
+    pub(crate) fn add_lock(
+        &mut self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        amount: Amount,
+        until_ms: u64,
+    ) {
+        assert!(
+            until_ms > env::block_timestamp_ms(),
+            "The lock must end in the future."
+        );
+        let key = (recipient.clone(), matcher.clone());
+        let existing_locked_amount = self.get_locked_amount(recipient, matcher);
+        let existing_until_ms = self.locks.get(&key).map(|lock| lock.until_ms).unwrap_or(0);
+        self.locks.insert(
+            &key,
+            &CommitmentLock {
+                amount: existing_locked_amount + amount,
+                until_ms: until_ms.max(existing_until_ms),
+            },
+        );
+    }
+
+    /// Returns the part of the commitment of `matcher` to `recipient` that currently can't be rescinded.
+    pub(crate) fn get_locked_amount(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) -> Amount {
+        match self.locks.get(&(recipient.clone(), matcher.clone())) {
+            Some(lock) if lock.until_ms > env::block_timestamp_ms() => lock.amount,
+            _ => 0,
+        }
+    }
+
+    /// Donations that get matched use up the locked part of a commitment first (since matching is exactly what the lock promised), so the locked amount can never exceed the commitment.
+    pub(crate) fn shrink_lock_to_commitment(
+        &mut self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        commitment: Amount,
+    ) {
+        let key = (recipient.clone(), matcher.clone());
+        if let Some(mut lock) = self.locks.get(&key) {
+            if commitment == 0 || lock.until_ms <= env::block_timestamp_ms() {
+                self.locks.remove(&key);
+            } else if lock.amount > commitment {
+                lock.amount = commitment;
+                self.locks.insert(&key, &lock);
+            }
+        }
+    }
+
+    /// Panics if the commitment of `matcher` to `recipient` would drop below its locked amount. Matching donations are not subject to this.
+    pub(crate) fn assert_not_below_locked_amount(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        new_amount: Amount,
+    ) {
+        let locked_amount = self.get_locked_amount(recipient, matcher);
+        assert!(
+            new_amount >= locked_amount,
+            "{} of the commitment of {} to {} is locked until {} (ms since the Unix epoch).",
+            yocto_to_near_string(&locked_amount),
+            matcher,
+            recipient,
+            self.locks
+                .get(&(recipient.clone(), matcher.clone()))
+                .map(|lock| lock.until_ms)
+                .unwrap_or(0)
+        );
+    }
+}
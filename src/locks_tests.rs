@@ -0,0 +1,84 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod locks_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::set_context_at;
+    use crate::Contract;
+
+    use near_sdk::test_utils::accounts;
+
+    const NOW: u64 = 1_700_000_000_000;
+    const LATER: u64 = NOW + 1_000_000;
+
+    #[test]
+    fn test_locked_commitment_is_shown_and_expires() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let unlocked_offer = near_string_to_yocto(&"0.1".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, Some(LATER));
+        set_context_at(1, unlocked_offer, NOW);
+        contract.offer_matching_funds(&recipient, None, None);
+        assert_eq!(
+            contract.get_commitment_details(&recipient),
+            format!(
+                "{{\"bob\":{{\"amount\":\"0.4 Ⓝ\",\"locked_amount\":\"0.3 Ⓝ\",\"locked_until_ms\":{}}}}}",
+                LATER
+            )
+        );
+
+        // The unlocked part can be rescinded before the lock expires:
+        set_context_at(1, 0, NOW);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+        assert_eq!(
+            contract.get_commitment_details(&recipient),
+            format!(
+                "{{\"bob\":{{\"amount\":\"0.3 Ⓝ\",\"locked_amount\":\"0.3 Ⓝ\",\"locked_until_ms\":{}}}}}",
+                LATER
+            )
+        );
+
+        // Matched donations use up the locked part:
+        set_context_at(3, unlocked_offer, NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        assert_eq!(
+            contract.get_commitment_details(&recipient),
+            format!(
+                "{{\"bob\":{{\"amount\":\"0.2 Ⓝ\",\"locked_amount\":\"0.2 Ⓝ\",\"locked_until_ms\":{}}}}}",
+                LATER
+            )
+        );
+
+        set_context_at(1, 0, LATER);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+        contract.rescind_matching_funds(&recipient, "0.2 Ⓝ".to_string(), None);
+        assert_eq!(contract.get_commitments(&recipient), "{}".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "0.3 Ⓝ of the commitment of bob to alice is locked until")]
+    fn test_rescind_below_locked_amount() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, Some(LATER));
+        set_context_at(1, 0, NOW);
+        contract.rescind_matching_funds(&recipient, "0.01 Ⓝ".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "0.3 Ⓝ of the commitment of bob to alice is locked until")]
+    fn test_rescind_all_with_locked_commitment() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, Some(LATER));
+        set_context_at(1, 0, NOW);
+        contract.rescind_all();
+    }
+}
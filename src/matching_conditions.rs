@@ -0,0 +1,146 @@
+// Matching conditions let a matcher limit which donations their commitment to a recipient matches, e.g. only donations of at least 1 Ⓝ, or only donations by employees of their company. Donations that don't meet a matcher's conditions are still accepted (and matched by the other matchers).
+
+use crate::generic::{self, near_string_to_yocto, storage_cost_since};
+use crate::{Amount, Contract, ContractExt, MatcherAccountId, RecipientAccountId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, near_bindgen, AccountId};
+
+pub const MAX_ALLOWED_DONORS: usize = 100;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MatchingConditions {
+    pub min_donation: Option<Amount>,
+    pub max_donation: Option<Amount>,
+    pub allowed_donors: Option<Vec<AccountId>>,
+    pub donor_suffix_pattern: Option<String>, // Such as "*.acme.near".
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MatchingConditionsView {
+    pub min_donation: Option<U128>,
+    pub max_donation: Option<U128>,
+    pub allowed_donors: Option<Vec<AccountId>>,
+    pub donor_suffix_pattern: Option<String>,
+}
+
+impl MatchingConditions {
+    /// The donation must be within the min and max (if given). If an allowlist and/or a suffix pattern is given, the donor must be on the allowlist or match the pattern.
+    fn allow(&self, donor: &AccountId, donation_amount: Amount) -> bool {
+        if self.min_donation.map_or(false, |min| donation_amount < min)
+            || self.max_donation.map_or(false, |max| donation_amount > max)
+        {
+            return false;
+        }
+        if self.allowed_donors.is_none() && self.donor_suffix_pattern.is_none() {
+            return true;
+        }
+        let is_allowed = self
+            .allowed_donors
+            .as_ref()
+            .map_or(false, |allowed_donors| allowed_donors.contains(donor));
+        let matches_pattern = self.donor_suffix_pattern.as_ref().map_or(false, |pattern| {
+            donor.as_str().ends_with(pattern.trim_start_matches('*'))
+        });
+        is_allowed || matches_pattern
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by a matcher to only match donations to `recipient` that meet these conditions (replacing any earlier ones). The conditions stay in place even if the commitment gets used up, so that a later offer or top-up is matched under the same rules.
+    /// While part of the commitment is locked, its conditions can't be set (since that could make the locked part unusable), only removed.
+    /// min_donation and max_donation are in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored). donor_suffix_pattern must look like "*.acme.near".
+    /// The attached deposit must cover the cost of storing the conditions and goes to this contract's reserve.
+    #[payable] // Public - People can attach money
+    pub fn set_matching_conditions(
+        &mut self,
+        recipient: AccountId,
+        min_donation: Option<generic::FormattedNearString>,
+        max_donation: Option<generic::FormattedNearString>,
+        allowed_donors: Option<Vec<AccountId>>,
+        donor_suffix_pattern: Option<String>,
+    ) {
+        let matcher = env::signer_account_id();
+        assert!(
+            self.get_locked_amount(&recipient, &matcher) == 0,
+            "Matching conditions can't be set while part of the commitment of {} to {} is locked.",
+            matcher,
+            recipient
+        );
+        let conditions = MatchingConditions {
+            min_donation: min_donation.map(|amount| near_string_to_yocto(&amount)),
+            max_donation: max_donation.map(|amount| near_string_to_yocto(&amount)),
+            allowed_donors,
+            donor_suffix_pattern,
+        };
+        if let (Some(min), Some(max)) = (conditions.min_donation, conditions.max_donation) {
+            assert!(
+                min <= max,
+                "The minimum donation can't be greater than the maximum donation."
+            );
+        }
+        if let Some(allowed_donors) = &conditions.allowed_donors {
+            assert!(
+                allowed_donors.len() <= MAX_ALLOWED_DONORS,
+                "At most {} donors can be allowed.",
+                MAX_ALLOWED_DONORS
+            );
+        }
+        if let Some(pattern) = &conditions.donor_suffix_pattern {
+            assert!(
+                pattern.starts_with("*.") && pattern.len() > 2 && !pattern[1..].contains('*'),
+                "The donor suffix pattern must look like \"*.acme.near\"."
+            );
+        }
+        let storage_usage_before = env::storage_usage();
+        self.matching_conditions
+            .insert(&(recipient, matcher), &conditions);
+        let conditions_storage_cost = storage_cost_since(storage_usage_before);
+        assert!(
+            env::attached_deposit() >= conditions_storage_cost,
+            "Attaching at least {} yoctoNEAR is required.",
+            conditions_storage_cost
+        );
+        self.add_to_reserve(env::attached_deposit());
+    }
+
+    pub fn remove_matching_conditions(&mut self, recipient: AccountId) {
+        let matcher = env::signer_account_id();
+        self.matching_conditions
+            .remove(&(recipient, matcher))
+            .expect("There are no matching conditions for this recipient.");
+    }
+
+    pub fn get_matching_conditions(
+        &self,
+        recipient: AccountId,
+        matcher: MatcherAccountId,
+    ) -> Option<MatchingConditionsView> {
+        self.matching_conditions
+            .get(&(recipient, matcher))
+            .map(|conditions| MatchingConditionsView {
+                min_donation: conditions.min_donation.map(U128),
+                max_donation: conditions.max_donation.map(U128),
+                allowed_donors: conditions.allowed_donors,
+                donor_suffix_pattern: conditions.donor_suffix_pattern,
+            })
+    }
+}
+
+impl Contract {
+    /// Whether the commitment of `matcher` to `recipient` should match this donation.
+    pub(crate) fn meets_matching_conditions(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+    ) -> bool {
+        self.matching_conditions
+            .get(&(recipient.clone(), matcher.clone()))
+            .map_or(true, |conditions| conditions.allow(donor, donation_amount))
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod matching_conditions_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_context, set_context_at};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId};
+
+    fn setup() -> Contract {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.5".to_string());
+        let storage_deposit = near_string_to_yocto(&"0.01".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(1, false, starting_balance, storage_deposit);
+        contract.set_matching_conditions(
+            recipient.clone(),
+            Some("0.1".to_string()),
+            Some("0.2".to_string()),
+            Some(vec![accounts(3)]), // 3 = Danny
+            Some("*.acme.near".to_string()),
+        );
+        set_context(2, false, starting_balance, starting_balance); // 2 = Charlie, who has no conditions
+        contract.offer_matching_funds(&recipient, None, None);
+        contract
+    }
+
+    fn donate(contract: &mut Contract, donor: AccountId, amount: &str) {
+        let context = VMContextBuilder::new()
+            .signer_account_id(donor)
+            .account_balance(near_string_to_yocto(&"1".to_string()))
+            .attached_deposit(near_string_to_yocto(&amount.to_string()))
+            .build();
+        testing_env!(context);
+        contract.donate(&accounts(0), None, None, None);
+    }
+
+    fn last_matched_amount(contract: &Contract) -> U128 {
+        contract
+            .get_donations(accounts(0), None, None)
+            .last()
+            .unwrap()
+            .matched_amount
+    }
+
+    #[test]
+    fn test_matching_conditions() {
+        let mut contract = setup();
+        let small = near_string_to_yocto(&"0.05".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let large = near_string_to_yocto(&"0.3".to_string());
+
+        donate(&mut contract, accounts(3), "0.1"); // On the allowlist.
+        assert_eq!(last_matched_amount(&contract), U128(2 * donation));
+
+        donate(&mut contract, "employee.acme.near".parse().unwrap(), "0.1"); // Matches the pattern.
+        assert_eq!(last_matched_amount(&contract), U128(2 * donation));
+
+        donate(&mut contract, "acme.near".parse().unwrap(), "0.1"); // Doesn't match the pattern.
+        assert_eq!(last_matched_amount(&contract), U128(donation));
+
+        donate(&mut contract, accounts(3), "0.05"); // Below the minimum.
+        assert_eq!(last_matched_amount(&contract), U128(small));
+
+        donate(&mut contract, accounts(3), "0.3"); // Above the maximum.
+        assert_eq!(last_matched_amount(&contract), U128(large));
+    }
+
+    #[test]
+    fn test_removing_matching_conditions() {
+        let mut contract = setup();
+        set_context(1, false, 0, 0); // 1 = Bob
+        contract.remove_matching_conditions(accounts(0));
+        assert_eq!(
+            contract.get_matching_conditions(accounts(0), accounts(1)),
+            None
+        );
+
+        donate(&mut contract, "acme.near".parse().unwrap(), "0.1");
+        assert_eq!(
+            last_matched_amount(&contract),
+            U128(2 * near_string_to_yocto(&"0.1".to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The donor suffix pattern must look like \"*.acme.near\".")]
+    fn test_invalid_suffix_pattern() {
+        let mut contract = Contract::new();
+        set_context(1, false, 0, near_string_to_yocto(&"0.01".to_string())); // 1 = Bob
+        contract.set_matching_conditions(
+            accounts(0),
+            None,
+            None,
+            None,
+            Some("acme.near".to_string()),
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Matching conditions can't be set while part of the commitment of bob to alice is locked."
+    )]
+    fn test_conditions_cannot_be_set_on_a_locked_commitment() {
+        let mut contract = Contract::new();
+        let now = 1_700_000_000_000;
+        set_context_at(1, near_string_to_yocto(&"0.5".to_string()), now); // 1 = Bob
+        contract.offer_matching_funds(&accounts(0), None, Some(now + 1_000_000));
+        set_context_at(1, near_string_to_yocto(&"0.01".to_string()), now);
+        contract.set_matching_conditions(accounts(0), Some("100".to_string()), None, None, None);
+    }
+
+    #[test]
+    fn test_conditions_are_charged_for_their_storage() {
+        let contract = setup();
+        let reserve = contract.get_reserve().0;
+        assert!(reserve > 0);
+        assert!(reserve <= near_string_to_yocto(&"0.01".to_string()));
+    }
+}
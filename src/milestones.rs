@@ -0,0 +1,441 @@
+// Milestone-based releases for grant-style recipients: donations to the recipient (and their matches) build up in a held balance, and each time the recipient's approver signs off the next milestone, a tranche of that balance is transferred to the recipient (minus the platform fee). If milestones are still unapproved at the deadline, each donor can claim their share of what is still held as a refund and each matcher can claim their share back into their commitment (like for a failed goal; see goals.rs).
+
+use crate::donations::MAX_MEMO_LENGTH;
+use crate::events::{DonationRefundEventData, Event};
+use crate::generic::{did_promise_succeed, mul_div, storage_cost_since, yocto_to_near_string};
+use crate::{
+    Amount, Contract, ContractExt, InMemoryMatcherAmountMap, RecipientAccountId,
+    GAS_FOR_ACCOUNT_CALLBACK,
+};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise};
+
+pub const MAX_MILESTONES_DURATION_MS: u64 = 2 * 365 * 24 * 60 * 60 * 1000; // 2 years, so that contributors never have their funds held indefinitely.
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Milestone {
+    pub description: String,
+    pub released_amount: Option<Amount>, // None until the approver signs off this milestone.
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MilestonePlan {
+    pub number: u64, // Counts this recipient's plans, so that contributions to an earlier plan aren't mixed up with this one.
+    pub approver: AccountId,
+    pub milestones: Vec<Milestone>,
+    pub deadline_ms: u64,
+    pub held: Amount,
+    pub unclaimed: Amount, // Contributions whose share of `held` hasn't been refunded yet.
+    pub matched: Amount, // The part of `unclaimed` that matchers contributed, so that each tranche's fee can be split the way `calculate_fee` splits a donation's.
+    pub transferring: Amount, // Tranches and refunds that are being transferred (and that are held again if their transfer fails).
+}
+
+/// What one account has contributed to a recipient's current milestone plan (as a donor, as a matcher, or both).
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct MilestoneContribution {
+    pub donated: Amount,
+    pub public_donated: Amount, // The part of `donated` that was not anonymous.
+    pub matched: Amount,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MilestoneView {
+    pub description: String,
+    pub approved: bool,
+    pub released_amount: U128,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MilestonePlanView {
+    pub approver: AccountId,
+    pub deadline_ms: u64,
+    pub held: U128,
+    pub milestones: Vec<MilestoneView>,
+}
+
+impl MilestonePlan {
+    fn next_milestone_index(&self) -> Option<usize> {
+        self.milestones
+            .iter()
+            .position(|milestone| milestone.released_amount.is_none())
+    }
+
+    fn has_expired(&self) -> bool {
+        env::block_timestamp_ms() >= self.deadline_ms && self.next_milestone_index().is_some()
+    }
+
+    /// Whether incoming donations (and their matches) are held for this plan.
+    fn is_active(&self) -> bool {
+        env::block_timestamp_ms() < self.deadline_ms && self.next_milestone_index().is_some()
+    }
+
+    /// Whether every milestone was approved (or the plan expired) and nothing is held or being transferred anymore.
+    fn is_settled(&self) -> bool {
+        !self.is_active() && self.held == 0 && self.transferring == 0
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by a recipient to hold all incoming donations and matches until `approver` signs off each of `milestone_descriptions` (in order). Each sign-off releases an equal share of what is held at that time (the last one releases everything). Whatever is still held at `deadline_ms` (at most `MAX_MILESTONES_DURATION_MS` from now) can be claimed back by its contributors (see `claim_milestone_refund`).
+    /// The attached deposit must cover the cost of storing the plan and goes to this contract's reserve.
+    #[payable] // Public - People can attach money
+    pub fn set_milestones(
+        &mut self,
+        approver: AccountId,
+        milestone_descriptions: Vec<String>,
+        deadline_ms: u64,
+    ) {
+        let recipient = env::signer_account_id();
+        assert!(
+            approver != recipient,
+            "The recipient can't be its own approver."
+        );
+        assert!(
+            !milestone_descriptions.is_empty(),
+            "At least one milestone is required."
+        );
+        assert!(
+            deadline_ms > env::block_timestamp_ms(),
+            "The deadline must be in the future."
+        );
+        assert!(
+            deadline_ms - env::block_timestamp_ms() <= MAX_MILESTONES_DURATION_MS,
+            "The deadline can't be more than {} ms from now.",
+            MAX_MILESTONES_DURATION_MS
+        );
+        let mut number = 0;
+        if let Some(plan) = self.milestone_plans.get(&recipient) {
+            assert!(
+                plan.is_settled(),
+                "{} already has milestones whose funds haven't all been released or refunded.",
+                recipient
+            );
+            number = plan.number + 1;
+        }
+        assert!(
+            !self.has_unreleased_goal(&recipient),
+            "Milestones can't be combined with a goal whose funds haven't been released."
+        );
+        for description in milestone_descriptions.iter() {
+            assert!(
+                description.len() <= MAX_MEMO_LENGTH,
+                "Milestone descriptions can be at most {} bytes long.",
+                MAX_MEMO_LENGTH
+            );
+        }
+        let storage_usage_before = env::storage_usage();
+        self.milestone_plans.insert(
+            &recipient,
+            &MilestonePlan {
+                number,
+                approver,
+                milestones: milestone_descriptions
+                    .into_iter()
+                    .map(|description| Milestone {
+                        description,
+                        released_amount: None,
+                    })
+                    .collect(),
+                deadline_ms,
+                held: 0,
+                unclaimed: 0,
+                matched: 0,
+                transferring: 0,
+            },
+        );
+        let plan_storage_cost = storage_cost_since(storage_usage_before);
+        let attached_deposit = env::attached_deposit();
+        assert!(
+            attached_deposit >= plan_storage_cost,
+            "Attaching at least {} yoctoNEAR is required.",
+            plan_storage_cost
+        );
+        self.add_to_reserve(attached_deposit);
+    }
+
+    pub fn get_milestones(&self, recipient: AccountId) -> Option<MilestonePlanView> {
+        self.milestone_plans
+            .get(&recipient)
+            .map(|plan| MilestonePlanView {
+                approver: plan.approver,
+                deadline_ms: plan.deadline_ms,
+                held: U128(plan.held),
+                milestones: plan
+                    .milestones
+                    .into_iter()
+                    .map(|milestone| MilestoneView {
+                        description: milestone.description,
+                        approved: milestone.released_amount.is_some(),
+                        released_amount: U128(milestone.released_amount.unwrap_or(0)),
+                    })
+                    .collect(),
+            })
+    }
+
+    /// Called by the approver to sign off the next milestone of `recipient`, which transfers its tranche of the held balance (minus the platform fee) to `recipient`.
+    pub fn approve_milestone(&mut self, recipient: AccountId, milestone_index: u64) {
+        let mut plan = self
+            .milestone_plans
+            .get(&recipient)
+            .expect("This recipient has no milestones.");
+        assert_eq!(
+            plan.approver,
+            env::signer_account_id(),
+            "Only the approver can approve milestones."
+        );
+        let index = plan
+            .next_milestone_index()
+            .expect("Every milestone has already been approved.");
+        assert!(
+            !plan.has_expired(),
+            "The deadline has passed, so contributors can claim refunds instead."
+        );
+        assert_eq!(
+            milestone_index as usize, index,
+            "Milestone {} is the next one to approve.",
+            index
+        );
+        let remaining_milestones = (plan.milestones.len() - index) as u128;
+        let tranche = plan.held / remaining_milestones; // For the last milestone, this is everything that is held.
+        let fee = if tranche > 0 {
+            let matched_part = mul_div(tranche, plan.matched, plan.unclaimed);
+            self.calculate_fee(tranche - matched_part, matched_part)
+        } else {
+            0
+        };
+        plan.held -= tranche;
+        plan.transferring += tranche;
+        plan.milestones[index].released_amount = Some(tranche);
+        self.milestone_plans.insert(&recipient, &plan);
+        self.total_held -= tranche;
+        log!(
+            "Milestone {} of {} was approved, releasing {} (minus a fee of {}).",
+            index,
+            recipient,
+            yocto_to_near_string(&tranche),
+            yocto_to_near_string(&fee)
+        );
+        if tranche > 0 {
+            self.pending_fees += fee;
+            self.transfer_from_escrow(&recipient, tranche - fee).then(
+                Self::ext(env::current_account_id()) // escrow contract name
+                    .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                    .on_approve_milestone(recipient, milestone_index, tranche, fee),
+            );
+        }
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_approve_milestone(
+        &mut self,
+        recipient: AccountId,
+        milestone_index: u64,
+        tranche: Amount,
+        fee: Amount,
+    ) {
+        // `set_milestones` can't replace a plan while its tranches are being transferred, so this is still the same plan:
+        let mut plan = self.milestone_plans.get(&recipient).unwrap();
+        plan.transferring -= tranche;
+        let succeeded = did_promise_succeed();
+        self.settle_pending_fee(fee, succeeded);
+        if !succeeded {
+            // If transfer failed, hold the tranche again and reopen the milestone so that the approver can retry:
+            plan.held += tranche;
+            plan.milestones[milestone_index as usize].released_amount = None;
+            self.total_held += tranche;
+        }
+        self.milestone_plans.insert(&recipient, &plan);
+    }
+
+    /// After the deadline of milestones that weren't all approved, refunds the caller's share of what is still held: in proportion to what they contributed, as a donation refund and/or back into their commitment to `recipient`.
+    /// Stats and leaderboards keep counting these contributions, since part of them may already have been released to `recipient`.
+    pub fn claim_milestone_refund(&mut self, recipient: AccountId) {
+        let account = env::signer_account_id();
+        let mut plan = self
+            .milestone_plans
+            .get(&recipient)
+            .expect("This recipient has no milestones.");
+        assert!(
+            plan.has_expired(),
+            "Refunds are only available after the deadline of milestones that weren't all approved."
+        );
+        let contribution = self
+            .milestone_contributions
+            .remove(&(recipient.clone(), plan.number, account.clone()))
+            .expect("You have nothing to claim from these milestones.");
+        let contributed = contribution.donated + contribution.matched;
+        let refund = mul_div(plan.held, contributed, plan.unclaimed);
+        let matched_refund = mul_div(refund, contribution.matched, contributed);
+        let donated_refund = refund - matched_refund;
+        plan.held -= refund;
+        plan.unclaimed -= contributed;
+        plan.matched -= contribution.matched;
+        plan.transferring += donated_refund;
+        self.milestone_plans.insert(&recipient, &plan);
+        self.total_held -= refund;
+        if matched_refund > 0 {
+            self.add_to_commitment(&recipient, &account, matched_refund);
+            self.total_escrowed += matched_refund;
+        }
+        if donated_refund > 0 {
+            Event::DonationRefund(DonationRefundEventData {
+                recipient: recipient.clone(),
+                donor: if contribution.public_donated > 0 {
+                    Some(account.clone())
+                } else {
+                    None
+                },
+                amount: U128(donated_refund),
+            })
+            .emit();
+            Promise::new(account.clone())
+                .transfer(donated_refund) // Not using transfer_from_escrow because its log would reveal anonymous donors.
+                .then(
+                    Self::ext(env::current_account_id()) // escrow contract name
+                        .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                        .on_claim_milestone_refund(
+                            recipient,
+                            account,
+                            donated_refund,
+                            contribution.donated,
+                            contribution.public_donated,
+                        ),
+                );
+        }
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_claim_milestone_refund(
+        &mut self,
+        recipient: AccountId,
+        donor: AccountId,
+        donated_refund: Amount,
+        donated: Amount,
+        public_donated: Amount,
+    ) {
+        // `set_milestones` can't replace a plan while its refunds are being transferred, so this is still the same plan:
+        let mut plan = self.milestone_plans.get(&recipient).unwrap();
+        plan.transferring -= donated_refund;
+        if !did_promise_succeed() {
+            // If transfer failed, hold the refund again so that the donor can retry their claim:
+            plan.held += donated_refund;
+            plan.unclaimed += donated;
+            self.total_held += donated_refund;
+            self.hold_milestone_contribution(
+                &recipient,
+                plan.number,
+                &donor,
+                donated,
+                public_donated,
+                0,
+            );
+        }
+        self.milestone_plans.insert(&recipient, &plan);
+    }
+}
+
+impl Contract {
+    /// If `recipient` has milestones left to approve before their deadline, holds this donation and its matching funds for them (instead of them being transferred) and returns true. The fee is charged when a milestone is approved.
+    pub(crate) fn hold_for_milestones(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+        matched_amounts: &InMemoryMatcherAmountMap,
+        anonymous: bool,
+    ) -> bool {
+        let mut plan = match self.milestone_plans.get(recipient) {
+            Some(plan) if plan.is_active() => plan,
+            _ => return false,
+        };
+        if donation_amount > 0 {
+            let public_donated = if anonymous { 0 } else { donation_amount };
+            self.hold_milestone_contribution(
+                recipient,
+                plan.number,
+                donor,
+                donation_amount,
+                public_donated,
+                0,
+            );
+        }
+        let mut matched_amount: Amount = 0;
+        for (matcher, matched_amount_for_this_matcher) in matched_amounts.iter() {
+            matched_amount += matched_amount_for_this_matcher;
+            self.hold_milestone_contribution(
+                recipient,
+                plan.number,
+                matcher,
+                0,
+                0,
+                *matched_amount_for_this_matcher,
+            );
+        }
+        let amount = donation_amount + matched_amount;
+        plan.held += amount;
+        plan.unclaimed += amount;
+        plan.matched += matched_amount;
+        self.milestone_plans.insert(recipient, &plan);
+        self.total_held += amount;
+        log!(
+            "Holding {} for the milestones of {}.",
+            yocto_to_near_string(&amount),
+            recipient
+        );
+        true
+    }
+
+    /// Takes the storage cost of a held donation (see send_donation) out of the donor's contribution, since it went to the reserve instead.
+    pub(crate) fn deduct_from_milestone_contribution(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        amount: Amount,
+        anonymous: bool,
+    ) {
+        let mut plan = self
+            .milestone_plans
+            .get(recipient)
+            .expect("No milestones found.");
+        let key = (recipient.clone(), plan.number, donor.clone());
+        let mut contribution = self.milestone_contributions.get(&key).unwrap_or_default();
+        contribution.donated -= amount;
+        if !anonymous {
+            contribution.public_donated -= amount;
+        }
+        self.milestone_contributions.insert(&key, &contribution);
+        plan.held -= amount;
+        plan.unclaimed -= amount;
+        self.milestone_plans.insert(recipient, &plan);
+        self.total_held -= amount;
+    }
+
+    pub(crate) fn has_unsettled_milestones(&self, recipient: &RecipientAccountId) -> bool {
+        self.milestone_plans
+            .get(recipient)
+            .map(|plan| !plan.is_settled())
+            .unwrap_or(false)
+    }
+
+    fn hold_milestone_contribution(
+        &mut self,
+        recipient: &RecipientAccountId,
+        plan_number: u64,
+        account: &AccountId,
+        donated: Amount,
+        public_donated: Amount,
+        matched: Amount,
+    ) {
+        let key = (recipient.clone(), plan_number, account.clone());
+        let mut contribution = self.milestone_contributions.get(&key).unwrap_or_default();
+        contribution.donated += donated;
+        contribution.public_donated += public_donated;
+        contribution.matched += matched;
+        self.milestone_contributions.insert(&key, &contribution);
+    }
+}
@@ -0,0 +1,245 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod milestones_tests {
+    use crate::generic::{mul_div, near_string_to_yocto};
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context_at};
+    use crate::milestones::MAX_MILESTONES_DURATION_MS;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+
+    const NOW: u64 = 1_700_000_000_000;
+    const DEADLINE: u64 = NOW + 90 * 24 * 60 * 60 * 1000;
+
+    fn setup() -> Contract {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW);
+        contract.set_milestones(
+            accounts(2), // 2 = Charlie
+            vec!["Prototype".to_string(), "Launch".to_string()],
+            DEADLINE,
+        );
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, near_string_to_yocto(&"0.1".to_string()), NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        contract
+    }
+
+    /// The part of Danny's donation that paid for its storage and so isn't held (see send_donation).
+    fn held_donation(contract: &Contract) -> u128 {
+        near_string_to_yocto(&"0.1".to_string())
+            - contract.get_donations(accounts(0), None, None)[0]
+                .storage_cost
+                .0
+    }
+
+    #[test]
+    fn test_milestones_release_held_funds_in_tranches() {
+        let mut contract = setup();
+        let recipient = accounts(0);
+        let held = held_donation(&contract) + near_string_to_yocto(&"0.1".to_string());
+        let tranche = held / 2;
+        assert_eq!(
+            contract.get_milestones(recipient.clone()).unwrap().held,
+            U128(held)
+        );
+        assert_eq!(contract.check_invariants().total_held, U128(held));
+
+        set_context_at(2, 0, NOW); // 2 = Charlie, the approver
+        contract.approve_milestone(recipient.clone(), 0);
+        let plan = contract.get_milestones(recipient.clone()).unwrap();
+        assert_eq!(plan.held, U128(held - tranche));
+        assert!(plan.milestones[0].approved);
+        assert_eq!(plan.milestones[0].released_amount, U128(tranche));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_approve_milestone(recipient.clone(), 0, tranche, 0);
+        let plan = contract.get_milestones(recipient.clone()).unwrap();
+        assert_eq!(plan.held, U128(held));
+        assert!(!plan.milestones[0].approved);
+
+        set_context_at(2, 0, NOW);
+        contract.approve_milestone(recipient.clone(), 0);
+        contract.approve_milestone(recipient.clone(), 1);
+        let plan = contract.get_milestones(recipient.clone()).unwrap();
+        assert_eq!(plan.held, U128(0));
+        assert_eq!(plan.milestones[1].released_amount, U128(held - tranche));
+        assert_eq!(contract.check_invariants().total_held, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the approver can approve milestones.")]
+    fn test_only_approver_can_approve_milestones() {
+        let mut contract = setup();
+        set_context_at(0, 0, NOW); // 0 = Alice, the recipient
+        contract.approve_milestone(accounts(0), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The recipient can't be its own approver.")]
+    fn test_recipient_cannot_be_its_own_approver() {
+        let mut contract = Contract::new();
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW); // 0 = Alice
+        contract.set_milestones(accounts(0), vec!["Launch".to_string()], DEADLINE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attaching at least")]
+    fn test_milestones_require_a_deposit_for_their_storage() {
+        let mut contract = Contract::new();
+        set_context_at(0, 1, NOW); // 0 = Alice
+        contract.set_milestones(accounts(2), vec!["Launch".to_string()], DEADLINE);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "alice already has milestones whose funds haven't all been released or refunded."
+    )]
+    fn test_milestones_cannot_be_replaced_while_a_tranche_is_pending() {
+        let mut contract = setup();
+        let recipient = accounts(0);
+        set_context_at(2, 0, NOW); // 2 = Charlie, the approver
+        contract.approve_milestone(recipient.clone(), 0);
+        contract.approve_milestone(recipient, 1); // Its transfer hasn't completed yet.
+
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW); // 0 = Alice
+        contract.set_milestones(accounts(2), vec!["Next".to_string()], DEADLINE);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The deadline has passed, so contributors can claim refunds instead."
+    )]
+    fn test_milestones_cannot_be_approved_after_the_deadline() {
+        let mut contract = setup();
+        set_context_at(2, 0, DEADLINE); // 2 = Charlie, the approver
+        contract.approve_milestone(accounts(0), 0);
+    }
+
+    #[test]
+    fn test_expired_milestones_refund_what_is_still_held() {
+        let mut contract = setup();
+        let recipient = accounts(0);
+        let donated = held_donation(&contract);
+        let held = donated + near_string_to_yocto(&"0.1".to_string());
+        let tranche = held / 2;
+        set_context_at(2, 0, NOW); // 2 = Charlie, the approver
+        contract.approve_milestone(recipient.clone(), 0); // Releases half of what is held.
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_approve_milestone(recipient.clone(), 0, tranche, 0);
+
+        set_context_at(3, 0, DEADLINE); // 3 = Danny, who contributed about half of what was held
+        contract.claim_milestone_refund(recipient.clone());
+        let refund = mul_div(held - tranche, donated, held);
+        assert_eq!(
+            contract.get_milestones(recipient.clone()).unwrap().held,
+            U128(held - tranche - refund)
+        );
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_claim_milestone_refund(
+            recipient.clone(),
+            accounts(3),
+            refund,
+            donated,
+            donated,
+        );
+        assert_eq!(
+            contract.get_milestones(recipient.clone()).unwrap().held,
+            U128(held - tranche)
+        );
+
+        set_context_at(1, 0, DEADLINE); // 1 = Bob, whose matching funds were the other half
+        contract.claim_milestone_refund(recipient.clone());
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.25 Ⓝ\"}".to_string()
+        );
+        set_context_at(3, 0, DEADLINE);
+        contract.claim_milestone_refund(recipient.clone()); // Retrying after the failed transfer.
+        assert_eq!(
+            contract.get_milestones(recipient.clone()).unwrap().held,
+            U128(0)
+        );
+        let report = contract.check_invariants();
+        assert_eq!(report.total_held, U128(0));
+        assert_eq!(report.total_escrowed, report.sum_of_commitments);
+
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_claim_milestone_refund(recipient, accounts(3), refund, donated, donated);
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), DEADLINE); // 0 = Alice
+        contract.set_milestones(accounts(2), vec!["Next".to_string()], DEADLINE + 1);
+        // Everything was refunded, so a new plan can start.
+    }
+
+    #[test]
+    fn test_milestone_fee_is_charged_when_approved() {
+        let mut contract = setup();
+        let recipient = accounts(0);
+        contract.set_fee_config(200, 100, accounts(5)); // 5 = Fargo
+        set_context_at(3, near_string_to_yocto(&"0.1".to_string()), NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        let donations = contract.get_donations(recipient.clone(), None, None);
+        assert_eq!(donations[1].fee, U128(0)); // Held in full, so that a refund can be in full too.
+        let matched = 2 * near_string_to_yocto(&"0.1".to_string());
+        let held = 2 * near_string_to_yocto(&"0.1".to_string())
+            - donations[0].storage_cost.0
+            - donations[1].storage_cost.0
+            + matched;
+        assert_eq!(
+            contract.get_milestones(recipient.clone()).unwrap().held,
+            U128(held)
+        );
+
+        set_context_at(2, 0, NOW); // 2 = Charlie, the approver
+        contract.approve_milestone(recipient.clone(), 0);
+        let tranche = held / 2;
+        let matched_part = mul_div(tranche, matched, held);
+        let fee = (tranche - matched_part) * 2 / 100 + matched_part / 100;
+        assert_eq!(contract.get_fee_config().pending_fees, U128(fee));
+
+        set_callback_context(PromiseResult::Successful(vec![]));
+        contract.on_approve_milestone(recipient, 0, tranche, fee);
+        assert_eq!(contract.get_fee_config().pending_fees, U128(0));
+        assert_eq!(contract.get_fee_config().accrued_fees, U128(fee));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Milestones can't be combined with a goal whose funds haven't been released."
+    )]
+    fn test_milestones_cannot_be_set_during_an_active_goal() {
+        let mut contract = Contract::new();
+        set_context_at(0, 0, NOW); // 0 = Alice
+        contract.set_goal("1".to_string(), DEADLINE); // Nothing has been donated to it yet.
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW);
+        contract.set_milestones(accounts(2), vec!["Launch".to_string()], DEADLINE);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "A goal can't be combined with milestones whose funds haven't all been released or refunded."
+    )]
+    fn test_goal_cannot_be_set_during_active_milestones() {
+        let mut contract = Contract::new();
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW); // 0 = Alice
+        contract.set_milestones(accounts(2), vec!["Launch".to_string()], DEADLINE); // Nothing has been donated to them yet.
+        set_context_at(0, 0, NOW);
+        contract.set_goal("1".to_string(), DEADLINE);
+    }
+
+    #[test]
+    #[should_panic(expected = "The deadline can't be more than 63072000000 ms from now.")]
+    fn test_milestones_deadline_is_capped() {
+        let mut contract = Contract::new();
+        set_context_at(0, near_string_to_yocto(&"0.01".to_string()), NOW); // 0 = Alice
+        contract.set_milestones(
+            accounts(2),
+            vec!["Launch".to_string()],
+            NOW + MAX_MILESTONES_DURATION_MS + 1,
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// Two-step rescinding: if a recipient (or the contract) has a notice period, matchers must announce a rescind with `request_rescind` and can only complete it with `execute_rescind` once the notice period has passed. Until then, the funds stay available for matching.
+// A commitment keeps the notice period that applied when it was made, so a recipient can shorten the notice period of existing commitments but can't lengthen it.
+
+use crate::generic::{self, near_string_to_yocto, yocto_to_near_string};
+use crate::{Amount, Contract, ContractExt, MatcherAccountId, RecipientAccountId, RescindResult};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId};
+
+pub const MAX_RESCIND_NOTICE_PERIOD_MS: u64 = 30 * 24 * 60 * 60 * 1000; // 30 days
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RescindRequest {
+    pub amount: Amount,
+    pub available_at_ms: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Called by a recipient to require that matchers give `notice_period_ms` milliseconds (at most 30 days) of notice before rescinding their commitments to this recipient. `None` falls back to the contract's default notice period. Commitments that were made under a shorter notice period keep it.
+    pub fn set_rescind_notice_period(&mut self, notice_period_ms: Option<u64>) {
+        let recipient = env::signer_account_id();
+        match notice_period_ms {
+            Some(notice_period_ms) => {
+                assert_valid_notice_period(notice_period_ms);
+                self.rescind_notice_periods
+                    .insert(&recipient, &notice_period_ms);
+            }
+            None => {
+                self.rescind_notice_periods.remove(&recipient);
+            }
+        }
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn set_default_rescind_notice_period(&mut self, notice_period_ms: u64) {
+        assert_valid_notice_period(notice_period_ms);
+        self.default_rescind_notice_period_ms = notice_period_ms;
+    }
+
+    pub fn get_rescind_notice_period(&self, recipient: AccountId) -> u64 {
+        self.rescind_notice_periods
+            .get(&recipient)
+            .unwrap_or(self.default_rescind_notice_period_ms)
+    }
+
+    /// The notice period that `matcher` must give before rescinding their commitment to `recipient`: the longest one that applied whenever they added to the commitment, or the recipient's current one if that is shorter.
+    pub fn get_commitment_rescind_notice_period(
+        &self,
+        recipient: AccountId,
+        matcher: MatcherAccountId,
+    ) -> u64 {
+        let current_notice_period_ms = self.get_rescind_notice_period(recipient.clone());
+        self.commitment_notice_periods
+            .get(&(recipient, matcher))
+            .map_or(current_notice_period_ms, |notice_period_ms| {
+                notice_period_ms.min(current_notice_period_ms)
+            })
+    }
+
+    /// Starts the notice period for rescinding `requested_withdrawal_amount` of the caller's commitment to `recipient`. Replaces any earlier request for the same recipient.
+    /// requested_withdrawal_amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn request_rescind(
+        &mut self,
+        recipient: &AccountId,
+        requested_withdrawal_amount: generic::FormattedNearString,
+    ) -> String {
+        let matcher = env::signer_account_id();
+        let matchers_for_this_recipient = self.get_expected_matchers_for_this_recipient(recipient);
+        self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
+        let amount: Amount = near_string_to_yocto(&requested_withdrawal_amount);
+        assert!(amount > 0, "The amount must be greater than 0.");
+        let available_at_ms = env::block_timestamp_ms()
+            .checked_add(
+                self.get_commitment_rescind_notice_period(recipient.clone(), matcher.clone()),
+            )
+            .expect("The notice period is too long.");
+        self.rescind_requests.insert(
+            &(recipient.clone(), matcher.clone()),
+            &RescindRequest {
+                amount,
+                available_at_ms,
+            },
+        );
+        let result = format!(
+            "{} can rescind up to {} of their commitment to {} starting at {} (ms since the Unix epoch).",
+            matcher,
+            yocto_to_near_string(&amount),
+            recipient,
+            available_at_ms
+        );
+        log!(result);
+        result
+    }
+
+    pub fn cancel_rescind_request(&mut self, recipient: &AccountId) {
+        let matcher = env::signer_account_id();
+        self.rescind_requests
+            .remove(&(recipient.clone(), matcher))
+            .expect("There is no pending rescind request.");
+    }
+
+    /// Completes a rescind requested via `request_rescind` once its notice period has passed. If some of the commitment was used for matching in the meantime, only what remains (up to the requested amount) gets rescinded.
+    pub fn execute_rescind(&mut self, recipient: &AccountId) -> RescindResult {
+        let matcher = env::signer_account_id();
+        let key = (recipient.clone(), matcher.clone());
+        let request = self
+            .rescind_requests
+            .get(&key)
+            .expect("There is no pending rescind request.");
+        assert!(
+            env::block_timestamp_ms() >= request.available_at_ms,
+            "This rescind request can't be executed until {} (ms since the Unix epoch).",
+            request.available_at_ms
+        );
+        let matchers_for_this_recipient = self.get_expected_matchers_for_this_recipient(recipient);
+        let amount_already_committed =
+            self.get_expected_commitment(recipient, &matchers_for_this_recipient, &matcher);
+        let amount_to_decrease = request.amount.min(amount_already_committed);
+        let new_amount = amount_already_committed - amount_to_decrease;
+        self.assert_not_below_locked_amount(recipient, &matcher, new_amount);
+        self.rescind_requests.remove(&key);
+        self.set_matcher_amount(recipient, &matcher, new_amount);
+        log!(
+            "{} is about to rescind {} of their commitment to {}.",
+            &matcher,
+            yocto_to_near_string(&amount_to_decrease),
+            recipient
+        );
+        let promise_index = self.send_rescinded_funds(recipient, &matcher, amount_to_decrease);
+        RescindResult {
+            requested: U128(request.amount),
+            rescinded: U128(amount_to_decrease),
+            remaining: U128(new_amount),
+            promise_index,
+        }
+    }
+}
+
+fn assert_valid_notice_period(notice_period_ms: u64) {
+    assert!(
+        notice_period_ms <= MAX_RESCIND_NOTICE_PERIOD_MS,
+        "The notice period can't be longer than {} ms.",
+        MAX_RESCIND_NOTICE_PERIOD_MS
+    );
+}
+
+impl Contract {
+    /// Panics if the commitment of `matcher` to `recipient` can't be withdrawn without notice (i.e. without `request_rescind`).
+    pub(crate) fn assert_no_rescind_notice_period(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) {
+        let notice_period_ms =
+            self.get_commitment_rescind_notice_period(recipient.clone(), matcher.clone());
+        assert!(
+            notice_period_ms == 0,
+            "Commitments to {} require {} ms of notice. Use request_rescind instead.",
+            recipient,
+            notice_period_ms
+        );
+    }
+
+    pub(crate) fn get_rescind_request(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+    ) -> Option<RescindRequest> {
+        self.rescind_requests
+            .get(&(recipient.clone(), matcher.clone()))
+    }
+}
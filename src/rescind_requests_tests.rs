@@ -0,0 +1,157 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod rescind_requests_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::set_context_at;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+
+    const NOW: u64 = 1_700_000_000_000;
+    const NOTICE_PERIOD_MS: u64 = 1_000_000;
+
+    #[test]
+    fn test_rescind_after_notice_period() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.2".to_string());
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS));
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(1, 0, NOW);
+        contract.request_rescind(&recipient, "0.25 Ⓝ".to_string());
+        assert_eq!(
+            contract.get_commitment_details(&recipient),
+            format!(
+                "{{\"bob\":{{\"amount\":\"0.3 Ⓝ\",\"pending_rescind_amount\":\"0.25 Ⓝ\",\"pending_rescind_available_at_ms\":{}}}}}",
+                NOW + NOTICE_PERIOD_MS
+            )
+        );
+
+        // The funds are still used for matching during the notice period:
+        set_context_at(3, donation, NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        assert_eq!(
+            contract
+                .get_stats(Some(recipient.clone()))
+                .outstanding_commitments,
+            U128(offer - donation)
+        );
+
+        // Only what is left of the commitment gets rescinded:
+        set_context_at(1, 0, NOW + NOTICE_PERIOD_MS);
+        let result = contract.execute_rescind(&recipient);
+        assert_eq!(
+            result.requested,
+            U128(near_string_to_yocto(&"0.25".to_string()))
+        );
+        assert_eq!(result.rescinded, U128(offer - donation));
+        assert_eq!(result.remaining, U128(0));
+        assert_eq!(contract.get_commitments(&recipient), "{}".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitments to alice require 1000000 ms of notice.")]
+    fn test_immediate_rescind_with_default_notice_period() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        contract.set_default_rescind_notice_period(NOTICE_PERIOD_MS);
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(1, 0, NOW);
+        contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "This rescind request can't be executed until")]
+    fn test_execute_rescind_before_notice_period_ends() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS));
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(1, 0, NOW);
+        contract.request_rescind(&recipient, "0.1 Ⓝ".to_string());
+        set_context_at(1, 0, NOW + NOTICE_PERIOD_MS - 1);
+        contract.execute_rescind(&recipient);
+    }
+
+    #[test]
+    fn test_longer_notice_period_only_applies_to_new_commitments() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS));
+        set_context_at(2, offer, NOW); // 2 = Charlie
+        contract.offer_matching_funds(&recipient, None, None);
+        assert_eq!(
+            contract.get_commitment_rescind_notice_period(recipient.clone(), accounts(2)),
+            NOTICE_PERIOD_MS
+        );
+
+        // Bob committed before the notice period was set, so he can still rescind right away:
+        set_context_at(1, 0, NOW);
+        let result = contract.rescind_matching_funds(&recipient, "0.1 Ⓝ".to_string(), None);
+        assert_eq!(
+            result.rescinded,
+            U128(near_string_to_yocto(&"0.1".to_string()))
+        );
+
+        // A shorter notice period applies to existing commitments right away:
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS / 2));
+        assert_eq!(
+            contract.get_commitment_rescind_notice_period(recipient.clone(), accounts(2)),
+            NOTICE_PERIOD_MS / 2
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitments to alice require 1000000 ms of notice.")]
+    fn test_top_up_applies_the_longer_notice_period() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        set_context_at(1, near_string_to_yocto(&"0.01".to_string()), NOW); // 1 = Bob, with a small commitment before the notice period was set
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS));
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), NOW);
+        contract.offer_matching_funds(&recipient, None, None);
+        assert_eq!(
+            contract.get_commitment_rescind_notice_period(recipient.clone(), accounts(1)),
+            NOTICE_PERIOD_MS
+        );
+        contract.rescind_matching_funds(&recipient, "0.3 Ⓝ".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitments to alice require 1000000 ms of notice.")]
+    fn test_transfer_commitment_requires_no_notice_period() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        set_context_at(0, 0, NOW);
+        contract.set_rescind_notice_period(Some(NOTICE_PERIOD_MS));
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(2, 0, NOW); // 2 = Charlie
+        contract.accept_commitment_transfers_from(accounts(1));
+        set_context_at(1, 0, NOW);
+        contract.transfer_commitment(&recipient, accounts(2), "0.3 Ⓝ".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The notice period can't be longer than 2592000000 ms.")]
+    fn test_notice_period_is_capped() {
+        let mut contract = Contract::new();
+        set_context_at(0, 0, NOW); // 0 = Alice
+        contract.set_rescind_notice_period(Some(u64::MAX));
+    }
+}
@@ -0,0 +1,58 @@
+// The reserve is the part of this contract's balance that the contract itself owns (as opposed to escrowed commitments and accrued fees): donor tips plus the storage costs that users pay for memos and pledge messages. It funds the contract's own storage and gas.
+
+use crate::{Amount, Contract, ContractExt};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, near_bindgen};
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceInvariantView {
+    pub account_balance: U128,
+    pub total_escrow: U128,
+    pub accrued_fees: U128,
+    pub pending_fees: U128,
+    pub total_vault_balance: U128,
+    pub total_held: U128,
+    pub reserve: U128,
+    pub holds: bool, // Whether account_balance >= total_escrow + accrued_fees + pending_fees + total_vault_balance + total_held + reserve.
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_reserve(&self) -> U128 {
+        U128(self.reserve)
+    }
+
+    /// Shows whether this contract holds at least as much as it owes (escrowed commitments, accrued and pending fees, vaults, and funds held for goals and milestones) plus its own reserve.
+    pub fn get_balance_invariant(&self) -> BalanceInvariantView {
+        let account_balance = env::account_balance();
+        let total_escrow = self.contract_stats.outstanding_commitments;
+        BalanceInvariantView {
+            account_balance: U128(account_balance),
+            total_escrow: U128(total_escrow),
+            accrued_fees: U128(self.accrued_fees),
+            pending_fees: U128(self.pending_fees),
+            total_vault_balance: U128(self.total_vault_balance),
+            total_held: U128(self.total_held),
+            reserve: U128(self.reserve),
+            holds: account_balance
+                >= total_escrow
+                    + self.accrued_fees
+                    + self.pending_fees
+                    + self.total_vault_balance
+                    + self.total_held
+                    + self.reserve,
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn add_to_reserve(&mut self, amount: Amount) {
+        self.reserve += amount;
+    }
+
+    pub(crate) fn subtract_from_reserve(&mut self, amount: Amount) {
+        self.reserve -= amount;
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod reserve_tests {
+    use crate::generic::{near_string_to_yocto, storage_cost};
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tip_goes_to_reserve_and_is_refunded_on_rollback() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        let tip = near_string_to_yocto(&"0.01".to_string());
+        let memo = "Thanks!".to_string();
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation + tip); // 3 = Danny
+        contract.donate(&recipient, None, Some(memo.clone()), Some(U128(tip)));
+
+        let donations = contract.get_donations(recipient.clone(), None, None);
+        let donation_storage_cost = donations[0].storage_cost.0;
+        assert!(donation_storage_cost > storage_cost(memo.len()));
+        assert_eq!(contract.get_reserve(), U128(tip + donation_storage_cost));
+        assert_eq!(donations[0].amount, U128(donation));
+        assert_eq!(donations[0].tip, U128(tip));
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &HashMap::from([(accounts(1), donation)]),
+            0,
+            false,
+            0,
+        );
+        assert_eq!(contract.get_reserve(), U128(donation_storage_cost)); // The record is still stored, so its storage cost is kept.
+    }
+
+    #[test]
+    fn test_balance_invariant() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+
+        let invariant = contract.get_balance_invariant();
+        assert_eq!(invariant.total_escrow, U128(offer));
+        assert!(invariant.holds);
+
+        set_context(1, true, offer - 1, 0);
+        assert!(!contract.get_balance_invariant().holds);
+    }
+}
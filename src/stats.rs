@@ -0,0 +1,230 @@
+// Running totals kept on-chain so that front-ends don't need an indexer.
+
+use crate::generic::hash_account_id;
+use crate::{Amount, Contract, ContractExt, MatcherAccountId, RecipientAccountId, StorageKey};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{log, near_bindgen, AccountId};
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct RecipientStats {
+    pub total_donated: Amount,
+    pub total_matched: Amount,
+    pub unique_donors: u64,
+    pub outstanding_commitments: Amount,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ContractStats {
+    pub total_donated: Amount,
+    pub total_matched: Amount,
+    pub unique_donors: u64,
+    pub active_matchers: u64,
+    pub outstanding_commitments: Amount,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatsView {
+    pub total_donated: U128,
+    pub total_matched: U128,
+    pub unique_donors: u64,
+    pub active_matchers: u64,
+    pub outstanding_commitments: U128,
+}
+
+type MatcherRecipientSet = UnorderedSet<RecipientAccountId>;
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the totals for `recipient`, or for the whole contract if no recipient is given.
+    pub fn get_stats(&self, recipient: Option<AccountId>) -> StatsView {
+        match recipient {
+            Some(recipient) => {
+                let stats = self.recipient_stats.get(&recipient).unwrap_or_default();
+                let active_matchers = self
+                    .recipients
+                    .get(&recipient)
+                    .map(|matchers_for_this_recipient| matchers_for_this_recipient.len())
+                    .unwrap_or(0);
+                StatsView {
+                    total_donated: U128(stats.total_donated),
+                    total_matched: U128(stats.total_matched),
+                    unique_donors: stats.unique_donors,
+                    active_matchers,
+                    outstanding_commitments: U128(stats.outstanding_commitments),
+                }
+            }
+            None => StatsView {
+                total_donated: U128(self.contract_stats.total_donated),
+                total_matched: U128(self.contract_stats.total_matched),
+                unique_donors: self.contract_stats.unique_donors,
+                active_matchers: self.contract_stats.active_matchers,
+                outstanding_commitments: U128(self.contract_stats.outstanding_commitments),
+            },
+        }
+    }
+}
+
+impl Contract {
+    fn create_new_matcher_recipient_set(matcher: &MatcherAccountId) -> MatcherRecipientSet {
+        MatcherRecipientSet::new(StorageKey::MatcherRecipientsInner {
+            hash: hash_account_id(&matcher.to_string()),
+        })
+    }
+
+    /// Must be called whenever a matcher's commitment to a recipient changes, so that the outstanding totals, the matcher→recipients index, the pledge messages, the locks, the rescind requests and notice periods, and `committed_recipients` stay in sync with `self.recipients`.
+    pub(crate) fn record_commitment_change(
+        &mut self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        old_amount: Amount,
+        new_amount: Amount,
+    ) {
+        if old_amount == new_amount {
+            return;
+        }
+        let mut stats = self.recipient_stats.get(recipient).unwrap_or_default();
+        if new_amount > old_amount {
+            let increase = new_amount - old_amount;
+            stats.outstanding_commitments += increase;
+            self.contract_stats.outstanding_commitments += increase;
+        } else {
+            let decrease = old_amount - new_amount;
+            stats.outstanding_commitments -= decrease;
+            self.contract_stats.outstanding_commitments -= decrease;
+        }
+        if stats.outstanding_commitments > 0 {
+            self.committed_recipients.insert(recipient);
+        } else {
+            self.committed_recipients.remove(recipient);
+        }
+        self.recipient_stats.insert(recipient, &stats);
+        if new_amount < old_amount {
+            self.shrink_lock_to_commitment(recipient, matcher, new_amount);
+        }
+
+        if new_amount > old_amount {
+            // Every top-up is bound by the stricter of the stored and the current notice period, so that an early (small) commitment can't carry a shorter notice period over to funds added after the recipient raised it.
+            let key = (recipient.clone(), matcher.clone());
+            let notice_period_ms = self
+                .commitment_notice_periods
+                .get(&key)
+                .unwrap_or(0)
+                .max(self.get_rescind_notice_period(recipient.clone()));
+            self.commitment_notice_periods
+                .insert(&key, &notice_period_ms);
+        }
+        if old_amount == 0 {
+            let mut recipients_for_this_matcher = self
+                .matcher_recipients
+                .get(matcher)
+                .unwrap_or_else(|| Self::create_new_matcher_recipient_set(matcher));
+            if recipients_for_this_matcher.is_empty() {
+                self.contract_stats.active_matchers += 1;
+            }
+            recipients_for_this_matcher.insert(recipient);
+            self.matcher_recipients
+                .insert(matcher, &recipients_for_this_matcher);
+        } else if new_amount == 0 {
+            self.pledge_messages
+                .remove(&(recipient.clone(), matcher.clone()));
+            self.rescind_requests
+                .remove(&(recipient.clone(), matcher.clone()));
+            self.commitment_notice_periods
+                .remove(&(recipient.clone(), matcher.clone()));
+            if let Some(mut recipients_for_this_matcher) = self.matcher_recipients.get(matcher) {
+                recipients_for_this_matcher.remove(recipient);
+                if recipients_for_this_matcher.is_empty() {
+                    log!("{} no longer has any commitments", &matcher);
+                    self.contract_stats.active_matchers -= 1;
+                    self.matcher_recipients.remove(matcher);
+                } else {
+                    self.matcher_recipients
+                        .insert(matcher, &recipients_for_this_matcher);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn record_donation(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+        matched_amount: Amount,
+    ) {
+        let mut stats = self.recipient_stats.get(recipient).unwrap_or_default();
+        stats.total_donated += donation_amount;
+        stats.total_matched += matched_amount;
+        self.contract_stats.total_donated += donation_amount;
+        self.contract_stats.total_matched += matched_amount;
+
+        let donor_key = (recipient.clone(), donor.clone());
+        let previous_total_for_recipient = self.recipient_donor_totals.get(&donor_key).unwrap_or(0);
+        if previous_total_for_recipient == 0 {
+            stats.unique_donors += 1;
+        }
+        self.recipient_donor_totals.insert(
+            &donor_key,
+            &(previous_total_for_recipient + donation_amount),
+        );
+        self.recipient_stats.insert(recipient, &stats);
+
+        let previous_total = self.donor_totals.get(donor).unwrap_or(0);
+        if previous_total == 0 {
+            self.contract_stats.unique_donors += 1;
+        }
+        self.donor_totals
+            .insert(donor, &(previous_total + donation_amount));
+    }
+
+    /// Reverses `record_donation` after a failed transfer.
+    pub(crate) fn unrecord_donation(
+        &mut self,
+        recipient: &RecipientAccountId,
+        donor: &AccountId,
+        donation_amount: Amount,
+        matched_amount: Amount,
+    ) {
+        let mut stats = self.recipient_stats.get(recipient).unwrap_or_default();
+        stats.total_donated -= donation_amount;
+        stats.total_matched -= matched_amount;
+        self.contract_stats.total_donated -= donation_amount;
+        self.contract_stats.total_matched -= matched_amount;
+
+        let donor_key = (recipient.clone(), donor.clone());
+        let remaining_total_for_recipient =
+            self.recipient_donor_totals.get(&donor_key).unwrap_or(0) - donation_amount;
+        if remaining_total_for_recipient == 0 {
+            stats.unique_donors -= 1;
+            self.recipient_donor_totals.remove(&donor_key);
+        } else {
+            self.recipient_donor_totals
+                .insert(&donor_key, &remaining_total_for_recipient);
+        }
+        self.recipient_stats.insert(recipient, &stats);
+
+        let remaining_total = self.donor_totals.get(donor).unwrap_or(0) - donation_amount;
+        if remaining_total == 0 {
+            self.contract_stats.unique_donors -= 1;
+            self.donor_totals.remove(donor);
+        } else {
+            self.donor_totals.insert(donor, &remaining_total);
+        }
+    }
+
+    /// Reverses the matching part of `record_donation` when held matching funds go back to a matcher's commitment (see goals.rs).
+    pub(crate) fn unrecord_matching(
+        &mut self,
+        recipient: &RecipientAccountId,
+        matched_amount: Amount,
+    ) {
+        let mut stats = self.recipient_stats.get(recipient).unwrap_or_default();
+        stats.total_matched -= matched_amount;
+        self.recipient_stats.insert(recipient, &stats);
+        self.contract_stats.total_matched -= matched_amount;
+    }
+}
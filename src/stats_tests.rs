@@ -0,0 +1,92 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod stats_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::stats::StatsView;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_get_stats_after_offers_rescind_and_donate() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, offer); // 2 = Charlie
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(2, false, starting_balance, 0);
+        contract.rescind_matching_funds(&recipient, "0.3 Ⓝ".to_string(), None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        set_context(3, false, starting_balance, donation);
+        contract.donate(&recipient, None, None, None);
+
+        let expected = StatsView {
+            total_donated: U128(2 * donation),
+            total_matched: U128(2 * donation),
+            unique_donors: 1,
+            active_matchers: 1,
+            outstanding_commitments: U128(offer - 2 * donation),
+        };
+        assert_eq!(contract.get_stats(Some(recipient)), expected);
+        assert_eq!(contract.get_stats(None), expected);
+        assert_eq!(
+            contract.get_stats(Some(accounts(4))),
+            StatsView {
+                total_donated: U128(0),
+                total_matched: U128(0),
+                unique_donors: 0,
+                active_matchers: 0,
+                outstanding_commitments: U128(0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_stats_after_failed_donation() {
+        let mut contract = Contract::new();
+        let starting_balance = near_string_to_yocto(&"1".to_string());
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.1".to_string());
+        let donation = near_string_to_yocto(&"0.2".to_string());
+        set_context(1, false, starting_balance, offer); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context(3, false, starting_balance, donation); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        assert_eq!(contract.get_stats(None).active_matchers, 0);
+        assert_eq!(contract.get_stats(None).unique_donors, 1);
+
+        let matched_amounts = HashMap::from([(accounts(1), offer)]);
+        set_callback_context(PromiseResult::Failed);
+        contract.on_donate(
+            &recipient,
+            accounts(3),
+            &donation,
+            &matched_amounts,
+            0,
+            false,
+            0,
+        );
+
+        let expected = StatsView {
+            total_donated: U128(0),
+            total_matched: U128(0),
+            unique_donors: 0,
+            active_matchers: 1,
+            outstanding_commitments: U128(offer),
+        };
+        assert_eq!(contract.get_stats(Some(recipient.clone())), expected);
+        assert_eq!(contract.get_stats(None), expected);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.1 Ⓝ\"}".to_string()
+        );
+    }
+}
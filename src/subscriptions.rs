@@ -0,0 +1,218 @@
+// Recurring donations: a donor deposits funds into their vault (see vaults.rs) and subscribes to give a fixed amount to a recipient at a fixed interval. Anyone can call `execute_due_subscriptions` (e.g. a cron-like keeper bot), which sends each due donation through the usual matching logic and pays the caller a small reward out of each donor's vault.
+
+use crate::generic::{
+    self, hash_account_id, near_string_to_yocto, storage_cost_since, yocto_to_near_string,
+};
+use crate::{Amount, Contract, ContractExt, RecipientAccountId, StorageKey};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId, Gas, Promise};
+
+pub const KEEPER_REWARD: Amount = 500_000_000_000_000_000_000; // 0.0005 Ⓝ per executed subscription, charged to the donor's vault.
+pub const MIN_SUBSCRIPTION_AMOUNT: Amount = 50_000_000_000_000_000_000_000; // 0.05 Ⓝ, several times what storing a donation costs (which send_donation deducts from it), so that a due donation never panics and blocks the rest.
+const DEFAULT_SUBSCRIPTIONS_LIMIT: u64 = 10;
+const GAS_FOR_EXECUTE_DUE_SUBSCRIPTIONS: Gas = Gas(10_000_000_000_000); // For `execute_due_subscriptions` itself (iterating and paying the keeper), on top of each donation's estimate.
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Subscription {
+    pub donor: AccountId,
+    pub recipient: RecipientAccountId,
+    pub amount: Amount,
+    pub interval_ms: u64,
+    pub next_due_ms: u64,
+    pub anonymous: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubscriptionView {
+    pub id: u64,
+    pub recipient: RecipientAccountId,
+    pub amount: U128,
+    pub interval_ms: u64,
+    pub next_due_ms: u64,
+    pub anonymous: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Subscribes the caller to donate `amount` (from their vault) to `recipient` every `interval_ms` milliseconds, starting now. The cost of storing the subscription is taken from the vault.
+    /// amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn create_subscription(
+        &mut self,
+        recipient: AccountId,
+        amount: generic::FormattedNearString,
+        interval_ms: u64,
+        anonymous: Option<bool>,
+    ) -> u64 {
+        let donor = env::signer_account_id();
+        let amount: Amount = near_string_to_yocto(&amount);
+        assert!(
+            amount >= MIN_SUBSCRIPTION_AMOUNT,
+            "The amount must be at least {}.",
+            yocto_to_near_string(&MIN_SUBSCRIPTION_AMOUNT)
+        );
+        assert!(interval_ms > 0, "The interval must be greater than 0.");
+        let storage_usage_before = env::storage_usage();
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        let next_due_ms = env::block_timestamp_ms();
+        self.subscriptions.insert(
+            &id,
+            &Subscription {
+                donor: donor.clone(),
+                recipient,
+                amount,
+                interval_ms,
+                next_due_ms,
+                anonymous: anonymous.unwrap_or(false),
+            },
+        );
+        let mut subscriptions_for_this_donor =
+            self.subscriptions_by_donor.get(&donor).unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::SubscriptionsByDonorInner {
+                    hash: hash_account_id(&donor.to_string()),
+                })
+            });
+        subscriptions_for_this_donor.insert(&id);
+        self.subscriptions_by_donor
+            .insert(&donor, &subscriptions_for_this_donor);
+        self.subscriptions_by_due_time
+            .insert(&(next_due_ms, id), &());
+        let subscription_storage_cost = storage_cost_since(storage_usage_before);
+        self.debit_vault(&donor, subscription_storage_cost);
+        self.add_to_reserve(subscription_storage_cost);
+        id
+    }
+
+    pub fn cancel_subscription(&mut self, id: u64) {
+        let subscription = self
+            .subscriptions
+            .get(&id)
+            .expect("There is no subscription with this ID.");
+        assert_eq!(
+            subscription.donor,
+            env::signer_account_id(),
+            "Only the donor can cancel this subscription."
+        );
+        self.subscriptions.remove(&id);
+        self.subscriptions_by_due_time
+            .remove(&(subscription.next_due_ms, id));
+        if let Some(mut subscriptions_for_this_donor) =
+            self.subscriptions_by_donor.get(&subscription.donor)
+        {
+            subscriptions_for_this_donor.remove(&id);
+            if subscriptions_for_this_donor.is_empty() {
+                self.subscriptions_by_donor.remove(&subscription.donor);
+            } else {
+                self.subscriptions_by_donor
+                    .insert(&subscription.donor, &subscriptions_for_this_donor);
+            }
+        }
+    }
+
+    /// Lists the donor's subscriptions, except for anonymous ones (which can be looked up by ID with `get_subscription`).
+    pub fn get_subscriptions(&self, donor: AccountId) -> Vec<SubscriptionView> {
+        self.subscriptions_by_donor
+            .get(&donor)
+            .map(|subscriptions_for_this_donor| {
+                subscriptions_for_this_donor
+                    .iter()
+                    .filter_map(|id| self.get_subscription(id))
+                    .filter(|subscription| !subscription.anonymous)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_subscription(&self, id: u64) -> Option<SubscriptionView> {
+        self.subscriptions
+            .get(&id)
+            .map(|subscription| SubscriptionView {
+                id,
+                recipient: subscription.recipient,
+                amount: U128(subscription.amount),
+                interval_ms: subscription.interval_ms,
+                next_due_ms: subscription.next_due_ms,
+                anonymous: subscription.anonymous,
+            })
+    }
+
+    /// Sends up to `limit` due donations, oldest due date first (stopping early if the attached gas runs low) and pays the caller `KEEPER_REWARD` for each one. Returns how many were sent.
+    /// A subscription whose donor can't cover the donation plus the reward, or whose recipient has never had any matchers, misses this payment (and will be due again after its interval).
+    /// If a donation's transfer fails, `on_donate` refunds it to the donor's account (not their vault).
+    /// Intervals that passed without a call (e.g. while no keeper was running) are skipped rather than made up, so each subscription donates at most once per call.
+    pub fn execute_due_subscriptions(&mut self, limit: Option<u64>) -> u64 {
+        let keeper = env::predecessor_account_id();
+        let now_ms = env::block_timestamp_ms();
+        let limit = limit.unwrap_or(DEFAULT_SUBSCRIPTIONS_LIMIT);
+        let due_keys: Vec<(u64, u64)> = self
+            .subscriptions_by_due_time
+            .iter()
+            .map(|(key, _)| key)
+            .take_while(|(next_due_ms, _)| *next_due_ms <= now_ms)
+            .take(limit as usize)
+            .collect();
+        let mut executed: u64 = 0;
+        for (next_due_ms, id) in due_keys {
+            let mut subscription = self.subscriptions.get(&id).unwrap();
+            let remaining_gas = env::prepaid_gas() - env::used_gas();
+            if remaining_gas
+                < GAS_FOR_EXECUTE_DUE_SUBSCRIPTIONS
+                    + self.estimate_gas_for_sending_donation(&subscription.recipient)
+                    + self.gas_for_employer_match(&subscription.donor)
+            {
+                log!("Stopping early because the attached gas is running low.");
+                break;
+            }
+            // Skip any intervals that were missed (e.g. while no keeper was running), so that a subscription donates at most once per call:
+            let missed_intervals = (now_ms - next_due_ms) / subscription.interval_ms;
+            subscription.next_due_ms += (missed_intervals + 1) * subscription.interval_ms;
+            self.subscriptions.insert(&id, &subscription);
+            self.subscriptions_by_due_time.remove(&(next_due_ms, id));
+            self.subscriptions_by_due_time
+                .insert(&(subscription.next_due_ms, id), &());
+            let vault_balance = self.get_vault_balance(subscription.donor.clone()).0;
+            if vault_balance < subscription.amount + KEEPER_REWARD {
+                if subscription.anonymous {
+                    log!(
+                        "Skipping subscription {} because its donor's vault is short.",
+                        id
+                    );
+                } else {
+                    log!(
+                        "Skipping subscription {} because {} only has {} in their vault.",
+                        id,
+                        subscription.donor,
+                        yocto_to_near_string(&vault_balance)
+                    );
+                }
+                continue;
+            }
+            if self.recipients.get(&subscription.recipient).is_none() {
+                log!(
+                    "Skipping subscription {} because {} has no matchers.",
+                    id,
+                    subscription.recipient
+                );
+                continue;
+            }
+            self.debit_vault(&subscription.donor, subscription.amount + KEEPER_REWARD);
+            self.send_donation(
+                &subscription.recipient,
+                subscription.donor,
+                subscription.amount,
+                subscription.anonymous,
+                None,
+                0,
+            );
+            executed += 1;
+        }
+        if executed > 0 {
+            Promise::new(keeper).transfer(KEEPER_REWARD * executed as u128);
+        }
+        executed
+    }
+}
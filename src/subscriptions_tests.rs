@@ -0,0 +1,167 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod subscriptions_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::set_context_at;
+    use crate::subscriptions::KEEPER_REWARD;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::{accounts, get_logs};
+
+    const NOW: u64 = 1_700_000_000_000;
+    const INTERVAL_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+    #[test]
+    fn test_due_subscriptions_are_donated_and_matched() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let deposit = near_string_to_yocto(&"1".to_string());
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        let amount = near_string_to_yocto(&"0.1".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, deposit, NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        let id =
+            contract.create_subscription(recipient.clone(), "0.1".to_string(), INTERVAL_MS, None);
+        let vault_after_storage = deposit - contract.get_reserve().0; // The storage cost goes to the reserve.
+        assert!(vault_after_storage < deposit);
+        assert_eq!(
+            contract.get_vault_balance(accounts(3)),
+            U128(vault_after_storage)
+        );
+
+        set_context_at(5, 0, NOW); // 5 = Fargo, a keeper
+        assert_eq!(contract.execute_due_subscriptions(None), 1);
+        assert_eq!(contract.execute_due_subscriptions(None), 0); // Not due again yet.
+        assert_eq!(
+            contract.get_vault_balance(accounts(3)),
+            U128(vault_after_storage - amount - KEEPER_REWARD)
+        );
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_subscriptions(accounts(3))[0].next_due_ms,
+            NOW + INTERVAL_MS
+        );
+
+        set_context_at(5, 0, NOW + INTERVAL_MS);
+        assert_eq!(contract.execute_due_subscriptions(None), 1);
+        assert_eq!(
+            contract.get_stats(Some(recipient)).total_donated,
+            U128(2 * amount)
+        );
+        assert_eq!(
+            contract.check_invariants().total_vault_balance,
+            contract.get_vault_balance(accounts(3))
+        );
+
+        set_context_at(3, 0, NOW + INTERVAL_MS);
+        contract.cancel_subscription(id);
+        assert_eq!(contract.get_subscriptions(accounts(3)), vec![]);
+    }
+
+    #[test]
+    fn test_missed_intervals_are_skipped() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        set_context_at(1, near_string_to_yocto(&"0.3".to_string()), NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, near_string_to_yocto(&"1".to_string()), NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        contract.create_subscription(recipient.clone(), "0.1".to_string(), INTERVAL_MS, None);
+
+        // No keeper ran for three and a half intervals:
+        set_context_at(5, 0, NOW + 3 * INTERVAL_MS + INTERVAL_MS / 2); // 5 = Fargo, a keeper
+        assert_eq!(contract.execute_due_subscriptions(None), 1);
+        assert_eq!(contract.execute_due_subscriptions(None), 0);
+        assert_eq!(
+            contract.get_subscriptions(accounts(3))[0].next_due_ms,
+            NOW + 4 * INTERVAL_MS
+        );
+    }
+
+    #[test]
+    fn test_subscription_is_skipped_when_vault_is_short() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let offer = near_string_to_yocto(&"0.3".to_string());
+        set_context_at(1, offer, NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, near_string_to_yocto(&"0.1".to_string()), NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        contract.create_subscription(
+            recipient.clone(),
+            "0.1".to_string(),
+            INTERVAL_MS,
+            Some(true),
+        );
+
+        set_context_at(5, 0, NOW); // 5 = Fargo, a keeper
+        assert_eq!(contract.execute_due_subscriptions(None), 0);
+        assert!(!get_logs().iter().any(|log| log.contains("danny")));
+        assert_eq!(contract.get_subscriptions(accounts(3)), vec![]); // Anonymous subscriptions aren't listed.
+        assert_eq!(
+            contract.get_subscription(0).unwrap().next_due_ms,
+            NOW + INTERVAL_MS
+        );
+        assert_eq!(contract.get_stats(Some(recipient)).total_donated, U128(0));
+    }
+
+    #[test]
+    fn test_only_due_subscriptions_are_executed_earliest_first() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let amount = near_string_to_yocto(&"0.1".to_string());
+        set_context_at(1, near_string_to_yocto(&"1".to_string()), NOW); // 1 = Bob
+        contract.offer_matching_funds(&recipient, None, None);
+        set_context_at(3, near_string_to_yocto(&"1".to_string()), NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        let monthly =
+            contract.create_subscription(recipient.clone(), "0.1".to_string(), INTERVAL_MS, None);
+        let daily = contract.create_subscription(
+            recipient.clone(),
+            "0.1".to_string(),
+            INTERVAL_MS / 30,
+            None,
+        );
+
+        set_context_at(5, 0, NOW); // 5 = Fargo, a keeper
+        assert_eq!(contract.execute_due_subscriptions(Some(1)), 1);
+        assert_eq!(
+            contract.get_subscription(monthly).unwrap().next_due_ms,
+            NOW + INTERVAL_MS
+        );
+        assert_eq!(contract.get_subscription(daily).unwrap().next_due_ms, NOW);
+        assert_eq!(contract.execute_due_subscriptions(None), 1);
+
+        set_context_at(5, 0, NOW + INTERVAL_MS / 30);
+        assert_eq!(contract.execute_due_subscriptions(None), 1); // Only the daily one is due.
+        assert_eq!(
+            contract.get_stats(Some(recipient)).total_donated,
+            U128(3 * amount)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the donor can cancel this subscription.")]
+    fn test_cancel_someone_elses_subscription() {
+        let mut contract = Contract::new();
+        set_context_at(3, near_string_to_yocto(&"1".to_string()), NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        let id = contract.create_subscription(accounts(0), "0.1".to_string(), INTERVAL_MS, None);
+        set_context_at(4, 0, NOW); // 4 = Eugene
+        contract.cancel_subscription(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount must be at least 0.05 Ⓝ.")]
+    fn test_subscription_amount_must_cover_a_donations_storage() {
+        let mut contract = Contract::new();
+        set_context_at(3, near_string_to_yocto(&"1".to_string()), NOW); // 3 = Danny
+        contract.deposit_to_vault();
+        contract.create_subscription(accounts(0), "0.001".to_string(), INTERVAL_MS, None);
+    }
+}
@@ -0,0 +1,170 @@
+// Recurring matching top-ups: a matcher keeps funds in their vault (see vaults.rs) and sets a rule per recipient such as "every quarter, replenish my commitment to this recipient back up to 100 Ⓝ". Anyone can call `execute_due_top_ups` to apply the rules that are due.
+
+use crate::generic::{self, near_string_to_yocto, storage_cost_since, yocto_to_near_string};
+use crate::{Amount, Contract, ContractExt, MatcherAccountId, RecipientAccountId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, log, near_bindgen, AccountId, Gas};
+
+const DEFAULT_TOP_UPS_LIMIT: u64 = 10;
+const GAS_PER_TOP_UP: Gas = Gas(5_000_000_000_000);
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TopUpRule {
+    pub target_amount: Amount,
+    pub interval_ms: u64,
+    pub next_due_ms: u64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TopUpView {
+    pub target_amount: U128,
+    pub interval_ms: u64,
+    pub next_due_ms: u64,
+    pub next_top_up_amount: U128, // What the next top-up would add if it happened now (limited by the remaining reserve).
+    pub remaining_reserve: U128,  // The matcher's vault balance.
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Every `interval_ms` milliseconds (starting at `first_top_up_ms`, or now), tops up the caller's commitment to `recipient` to `target_amount` using funds from the caller's vault. Replaces any existing rule for this recipient.
+    /// target_amount is in NEAR (commas, underscores, spaces, and 'Ⓝ' are acceptable and will be ignored)
+    pub fn set_top_up_rule(
+        &mut self,
+        recipient: AccountId,
+        target_amount: generic::FormattedNearString,
+        interval_ms: u64,
+        first_top_up_ms: Option<u64>,
+    ) {
+        let matcher = env::signer_account_id();
+        let target_amount: Amount = near_string_to_yocto(&target_amount);
+        assert!(
+            target_amount > 0,
+            "The target amount must be greater than 0."
+        );
+        assert!(interval_ms > 0, "The interval must be greater than 0.");
+        let storage_usage_before = env::storage_usage();
+        let key = (recipient, matcher.clone());
+        if let Some(old_rule) = self.top_up_rules.get(&key) {
+            self.unschedule_top_up(&key, &old_rule);
+        }
+        let rule = TopUpRule {
+            target_amount,
+            interval_ms,
+            next_due_ms: first_top_up_ms.unwrap_or_else(env::block_timestamp_ms),
+        };
+        self.top_up_rules.insert(&key, &rule);
+        self.schedule_top_up(&key, &rule);
+        let rule_storage_cost = storage_cost_since(storage_usage_before);
+        self.debit_vault(&matcher, rule_storage_cost);
+        self.add_to_reserve(rule_storage_cost);
+    }
+
+    pub fn remove_top_up_rule(&mut self, recipient: AccountId) {
+        let matcher = env::signer_account_id();
+        let key = (recipient, matcher);
+        let rule = self
+            .top_up_rules
+            .remove(&key)
+            .expect("There is no top-up rule for this recipient.");
+        self.unschedule_top_up(&key, &rule);
+    }
+
+    pub fn get_next_top_up(
+        &self,
+        recipient: AccountId,
+        matcher: MatcherAccountId,
+    ) -> Option<TopUpView> {
+        let rule = self
+            .top_up_rules
+            .get(&(recipient.clone(), matcher.clone()))?;
+        let remaining_reserve = self.get_vault_balance(matcher.clone()).0;
+        Some(TopUpView {
+            target_amount: U128(rule.target_amount),
+            interval_ms: rule.interval_ms,
+            next_due_ms: rule.next_due_ms,
+            next_top_up_amount: U128(self.get_top_up_amount(&recipient, &matcher, &rule)),
+            remaining_reserve: U128(remaining_reserve),
+        })
+    }
+
+    /// Applies up to `limit` top-up rules that are due, oldest due date first (stopping early if the attached gas runs low) and returns how many commitments were increased. Each due rule is then scheduled for one interval later, even if the matcher's vault couldn't cover it.
+    pub fn execute_due_top_ups(&mut self, limit: Option<u64>) -> u64 {
+        let now_ms = env::block_timestamp_ms();
+        let due_keys: Vec<(RecipientAccountId, MatcherAccountId)> = self
+            .top_ups_by_due_time
+            .iter()
+            .map(|(key, _)| key)
+            .take_while(|(next_due_ms, _, _)| *next_due_ms <= now_ms)
+            .map(|(_, recipient, matcher)| (recipient, matcher))
+            .take(limit.unwrap_or(DEFAULT_TOP_UPS_LIMIT) as usize)
+            .collect();
+        let mut topped_up: u64 = 0;
+        for key in due_keys {
+            if env::prepaid_gas() - env::used_gas() < GAS_PER_TOP_UP {
+                log!("Stopping early because the attached gas is running low.");
+                break;
+            }
+            let (recipient, matcher) = &key;
+            let mut rule = self.top_up_rules.get(&key).unwrap();
+            let amount = self.get_top_up_amount(recipient, matcher, &rule);
+            self.unschedule_top_up(&key, &rule);
+            rule.next_due_ms += rule.interval_ms;
+            self.top_up_rules.insert(&key, &rule);
+            self.schedule_top_up(&key, &rule);
+            if amount == 0 {
+                continue;
+            }
+            if !self.has_room_for_matcher(recipient, matcher) {
+                log!(
+                    "Skipping the top-up of {} to {} because that recipient already has the maximum number of matchers.",
+                    matcher,
+                    recipient
+                );
+                continue;
+            }
+            self.debit_vault(matcher, amount);
+            self.add_to_commitment(recipient, matcher, amount);
+            self.total_escrowed += amount;
+            log!(
+                "Topped up the commitment of {} to {} by {}.",
+                matcher,
+                recipient,
+                yocto_to_near_string(&amount)
+            );
+            topped_up += 1;
+        }
+        topped_up
+    }
+}
+
+impl Contract {
+    fn schedule_top_up(&mut self, key: &(RecipientAccountId, MatcherAccountId), rule: &TopUpRule) {
+        self.top_ups_by_due_time
+            .insert(&(rule.next_due_ms, key.0.clone(), key.1.clone()), &());
+    }
+
+    fn unschedule_top_up(
+        &mut self,
+        key: &(RecipientAccountId, MatcherAccountId),
+        rule: &TopUpRule,
+    ) {
+        self.top_ups_by_due_time
+            .remove(&(rule.next_due_ms, key.0.clone(), key.1.clone()));
+    }
+
+    /// How much would bring the commitment up to the rule's target, limited by what's left in the matcher's vault.
+    fn get_top_up_amount(
+        &self,
+        recipient: &RecipientAccountId,
+        matcher: &MatcherAccountId,
+        rule: &TopUpRule,
+    ) -> Amount {
+        let shortfall = rule
+            .target_amount
+            .saturating_sub(self.get_commitment(recipient, matcher));
+        shortfall.min(self.get_vault_balance(matcher.clone()).0)
+    }
+}
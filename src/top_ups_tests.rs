@@ -0,0 +1,113 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod top_ups_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::set_context_at;
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+
+    const NOW: u64 = 1_700_000_000_000;
+    const QUARTER_MS: u64 = 91 * 24 * 60 * 60 * 1000;
+
+    #[test]
+    fn test_top_ups_replenish_commitment_on_schedule() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let deposit = near_string_to_yocto(&"1".to_string());
+        let target = near_string_to_yocto(&"0.3".to_string());
+        let donation = near_string_to_yocto(&"0.1".to_string());
+        set_context_at(1, deposit, NOW); // 1 = Bob
+        contract.deposit_to_vault();
+        contract.set_top_up_rule(recipient.clone(), "0.3".to_string(), QUARTER_MS, None);
+        let reserve_after_storage = deposit - contract.get_reserve().0; // The storage cost goes to the contract's reserve.
+        let top_up = contract
+            .get_next_top_up(recipient.clone(), accounts(1))
+            .unwrap();
+        assert_eq!(top_up.next_due_ms, NOW);
+        assert_eq!(top_up.next_top_up_amount, U128(target));
+        assert_eq!(top_up.remaining_reserve, U128(reserve_after_storage));
+
+        set_context_at(5, 0, NOW); // 5 = Fargo, anyone can crank
+        assert_eq!(contract.execute_due_top_ups(None), 1);
+        assert_eq!(contract.execute_due_top_ups(None), 0); // Not due again yet.
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+
+        set_context_at(3, donation, NOW); // 3 = Danny
+        contract.donate(&recipient, None, None, None);
+        let top_up = contract
+            .get_next_top_up(recipient.clone(), accounts(1))
+            .unwrap();
+        assert_eq!(top_up.next_due_ms, NOW + QUARTER_MS);
+        assert_eq!(top_up.next_top_up_amount, U128(donation));
+
+        set_context_at(5, 0, NOW + QUARTER_MS);
+        assert_eq!(contract.execute_due_top_ups(None), 1);
+        assert_eq!(
+            contract.get_commitments(&recipient),
+            "{\"bob\":\"0.3 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract.get_vault_balance(accounts(1)),
+            U128(reserve_after_storage - target - donation)
+        );
+        assert_eq!(contract.check_invariants().sum_of_commitments, U128(target));
+    }
+
+    #[test]
+    fn test_top_up_is_limited_by_reserve() {
+        let mut contract = Contract::new();
+        let recipient = accounts(0); // 0 = Alice
+        let deposit = near_string_to_yocto(&"0.1".to_string());
+        set_context_at(1, deposit, NOW); // 1 = Bob
+        contract.deposit_to_vault();
+        contract.set_top_up_rule(recipient.clone(), "100".to_string(), QUARTER_MS, None);
+
+        set_context_at(5, 0, NOW); // 5 = Fargo
+        assert_eq!(contract.execute_due_top_ups(None), 1);
+        assert_eq!(contract.get_vault_balance(accounts(1)), U128(0));
+        assert_eq!(
+            contract
+                .get_next_top_up(recipient, accounts(1))
+                .unwrap()
+                .next_top_up_amount,
+            U128(0)
+        );
+    }
+
+    #[test]
+    fn test_only_due_top_ups_are_applied() {
+        let mut contract = Contract::new();
+        set_context_at(1, near_string_to_yocto(&"1".to_string()), NOW); // 1 = Bob
+        contract.deposit_to_vault();
+        contract.set_top_up_rule(accounts(0), "0.1".to_string(), QUARTER_MS, Some(NOW)); // 0 = Alice
+        contract.set_top_up_rule(
+            accounts(2), // 2 = Charlie
+            "0.1".to_string(),
+            QUARTER_MS,
+            Some(NOW + QUARTER_MS),
+        );
+        contract.set_top_up_rule(accounts(0), "0.2".to_string(), QUARTER_MS, Some(NOW)); // Replaces the first rule.
+
+        set_context_at(5, 0, NOW); // 5 = Fargo
+        assert_eq!(contract.execute_due_top_ups(None), 1);
+        assert_eq!(
+            contract.get_commitments(&accounts(0)),
+            "{\"bob\":\"0.2 Ⓝ\"}".to_string()
+        );
+        assert_eq!(
+            contract
+                .get_stats(Some(accounts(2)))
+                .outstanding_commitments,
+            U128(0)
+        );
+
+        set_context_at(1, 0, NOW);
+        contract.remove_top_up_rule(accounts(2));
+        set_context_at(5, 0, NOW + QUARTER_MS);
+        assert_eq!(contract.execute_due_top_ups(None), 0); // Alice's commitment is still at its target.
+    }
+}
@@ -0,0 +1,76 @@
+// A vault holds funds that an account has deposited into this contract ahead of time, so that recurring donations (see subscriptions.rs) and recurring matching top-ups (see top_ups.rs) can be paid without the account signing each payment.
+
+use crate::generic::{did_promise_succeed, yocto_to_near_string};
+use crate::{Amount, Contract, ContractExt, GAS_FOR_ACCOUNT_CALLBACK};
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, Promise};
+
+#[near_bindgen]
+impl Contract {
+    #[payable] // Public - People can attach money
+    pub fn deposit_to_vault(&mut self) -> U128 {
+        let account = env::signer_account_id();
+        let attached_deposit: Amount = env::attached_deposit();
+        assert!(attached_deposit > 0, "Attach the amount to deposit.");
+        let balance = self.get_vault_balance(account.clone()).0 + attached_deposit;
+        self.vaults.insert(&account, &balance);
+        self.total_vault_balance += attached_deposit;
+        U128(balance)
+    }
+
+    /// `amount` is in yoctoNEAR, so that a vault can be emptied exactly. If it is omitted, everything in the caller's vault is withdrawn.
+    pub fn withdraw_from_vault(&mut self, amount: Option<U128>) -> Promise {
+        let account = env::signer_account_id();
+        let amount: Amount = amount
+            .map(|amount| amount.0)
+            .unwrap_or_else(|| self.get_vault_balance(account.clone()).0);
+        assert!(amount > 0, "The amount must be greater than 0.");
+        self.debit_vault(&account, amount);
+        log!(
+            "{} is withdrawing {} from their vault.",
+            account,
+            yocto_to_near_string(&amount)
+        );
+        self.transfer_from_escrow(&account, amount).then(
+            Self::ext(env::current_account_id()) // escrow contract name
+                .with_static_gas(GAS_FOR_ACCOUNT_CALLBACK)
+                .on_withdraw_from_vault(account, amount),
+        )
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn on_withdraw_from_vault(&mut self, account: AccountId, amount: Amount) {
+        if !did_promise_succeed() {
+            // If transfer failed, the funds are still in the vault:
+            self.credit_vault(&account, amount);
+        }
+    }
+
+    pub fn get_vault_balance(&self, account: AccountId) -> U128 {
+        U128(self.vaults.get(&account).unwrap_or(0))
+    }
+}
+
+impl Contract {
+    pub(crate) fn debit_vault(&mut self, account: &AccountId, amount: Amount) {
+        let balance = self.get_vault_balance(account.clone()).0;
+        assert!(
+            amount <= balance,
+            "{} only has {} in their vault.",
+            account,
+            yocto_to_near_string(&balance)
+        );
+        if balance == amount {
+            self.vaults.remove(account);
+        } else {
+            self.vaults.insert(account, &(balance - amount));
+        }
+        self.total_vault_balance -= amount;
+    }
+
+    pub(crate) fn credit_vault(&mut self, account: &AccountId, amount: Amount) {
+        let balance = self.get_vault_balance(account.clone()).0;
+        self.vaults.insert(account, &(balance + amount));
+        self.total_vault_balance += amount;
+    }
+}
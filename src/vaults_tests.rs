@@ -0,0 +1,55 @@
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod vaults_tests {
+    use crate::generic::near_string_to_yocto;
+    use crate::lib_tests::lib_tests::{set_callback_context, set_context};
+    use crate::Contract;
+
+    use near_sdk::json_types::U128;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::PromiseResult;
+
+    #[test]
+    fn test_deposit_and_withdraw_from_vault() {
+        let mut contract = Contract::new();
+        let deposit = near_string_to_yocto(&"1".to_string());
+        let withdrawal = 123_456_789; // Finer than the 4 decimal places that NEAR strings allow.
+        set_context(3, false, deposit, deposit); // 3 = Danny
+        assert_eq!(contract.deposit_to_vault(), U128(deposit));
+        assert_eq!(contract.deposit_to_vault(), U128(2 * deposit));
+
+        set_context(3, false, 2 * deposit, 0);
+        contract.withdraw_from_vault(Some(U128(withdrawal)));
+        assert_eq!(
+            contract.get_vault_balance(accounts(3)),
+            U128(2 * deposit - withdrawal)
+        );
+
+        set_callback_context(PromiseResult::Failed);
+        contract.on_withdraw_from_vault(accounts(3), withdrawal);
+        assert_eq!(contract.get_vault_balance(accounts(3)), U128(2 * deposit)); // The failed withdrawal is back in the vault.
+
+        set_context(3, false, 2 * deposit, 0);
+        contract.withdraw_from_vault(None); // Everything.
+        assert_eq!(contract.get_vault_balance(accounts(3)), U128(0));
+        assert_eq!(contract.check_invariants().total_vault_balance, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "danny only has 1 Ⓝ in their vault.")]
+    fn test_withdraw_more_than_vault_balance() {
+        let mut contract = Contract::new();
+        let deposit = near_string_to_yocto(&"1".to_string());
+        set_context(3, false, deposit, deposit); // 3 = Danny
+        contract.deposit_to_vault();
+        set_context(3, false, deposit, 0);
+        contract.withdraw_from_vault(Some(U128(deposit + 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount must be greater than 0.")]
+    fn test_withdraw_from_empty_vault() {
+        let mut contract = Contract::new();
+        set_context(3, false, 0, 0); // 3 = Danny
+        contract.withdraw_from_vault(None);
+    }
+}
@@ -5,6 +5,10 @@
 use std::cmp;
 
 use anyhow::Error;
+use donation_matcher_contract::gas::{
+    GAS_FOR_ON_RESCIND_MATCHING_FUNDS, MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT,
+    MAX_MATCHERS_PER_RECIPIENT,
+};
 use donation_matcher_contract::generic::{near_string_to_yocto, yocto_to_near_string};
 use near_sdk::{log, serde_json::json, Balance};
 use test_log::test;
@@ -295,3 +299,285 @@ async fn test_offer_matching_funds_and_get_commitments_and_rescind_matching_fund
 
     Ok(())
 }
+
+#[test(tokio::test)]
+async fn test_eligibility_check_calls_a_token_contract() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let contract = worker
+        .dev_deploy(&include_bytes!("../target/res/donation_matcher_contract.wasm").to_vec())
+        .await?;
+    let token = worker
+        .dev_deploy(
+            &include_bytes!("mock-token/target/wasm32-unknown-unknown/release/mock_token.wasm")
+                .to_vec(),
+        )
+        .await?; // See tests/mock-token/src/lib.rs for how to build it.
+    contract.call(&worker, "new").max_gas().transact().await?;
+    token.call(&worker, "new").max_gas().transact().await?;
+    let parent_account = worker.dev_create_account().await?;
+
+    let starting_balance_for_each_acct = "10 Ⓝ".to_string();
+    let matcher_offer = "0.3 Ⓝ".to_string();
+    let donation = "0.1 Ⓝ".to_string();
+    let recipient = create_subaccount(
+        &worker,
+        &parent_account,
+        "recipient",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+    let matcher = create_subaccount(
+        &worker,
+        &parent_account,
+        "matcher",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+    let eligible_donor = create_subaccount(
+        &worker,
+        &parent_account,
+        "eligible_donor",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+    let ineligible_donor = create_subaccount(
+        &worker,
+        &parent_account,
+        "ineligible_donor",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+
+    token
+        .call(&worker, "set_balance")
+        .args_json(json!({"account_id": &eligible_donor.id(), "balance": "1"}))?
+        .max_gas()
+        .transact()
+        .await?;
+    matcher
+        .call(&worker, contract.id(), "offer_matching_funds")
+        .args_json(json!({"recipient": &recipient.id()}))?
+        .max_gas()
+        .deposit(near_string_to_yocto(&matcher_offer))
+        .transact()
+        .await?;
+    matcher
+        .call(&worker, contract.id(), "set_eligibility_check")
+        .args_json(json!({
+            "recipient": &recipient.id(),
+            "contract_id": &token.id(),
+            "method": "nft_supply_for_owner",
+            "min_balance": "1"
+        }))?
+        .max_gas()
+        .deposit(near_string_to_yocto(&"0.01".to_string()))
+        .transact()
+        .await?;
+
+    // The check's callback matches the donation of a donor who holds the token:
+    eligible_donor
+        .call(&worker, contract.id(), "donate")
+        .args_json(json!({"recipient": &recipient.id()}))?
+        .max_gas()
+        .deposit(near_string_to_yocto(&donation))
+        .transact()
+        .await?;
+    let matcher_offer_after_donation =
+        near_string_to_yocto(&matcher_offer) - near_string_to_yocto(&donation);
+    assert_expected_commitments(
+        &contract,
+        &worker,
+        &recipient,
+        json!({
+            matcher.id().to_string(): yocto_to_near_string(&matcher_offer_after_donation),
+        }),
+    )
+    .await?;
+    let recipient_expected_bal =
+        near_string_to_yocto(&starting_balance_for_each_acct) + 2 * near_string_to_yocto(&donation);
+    assert_approx_considering_gas(
+        &recipient.view_account(&worker).await?.balance,
+        &recipient_expected_bal,
+    );
+
+    // A donor who doesn't hold the token isn't matched:
+    ineligible_donor
+        .call(&worker, contract.id(), "donate")
+        .args_json(json!({"recipient": &recipient.id()}))?
+        .max_gas()
+        .deposit(near_string_to_yocto(&donation))
+        .transact()
+        .await?;
+    assert_expected_commitments(
+        &contract,
+        &worker,
+        &recipient,
+        json!({
+            matcher.id().to_string(): yocto_to_near_string(&matcher_offer_after_donation),
+        }),
+    )
+    .await?;
+    assert_approx_considering_gas(
+        &recipient.view_account(&worker).await?.balance,
+        &(recipient_expected_bal + near_string_to_yocto(&donation)),
+    );
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_failed_rescind_callback_fits_its_gas_budget() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let contract = worker
+        .dev_deploy(&include_bytes!("../target/res/donation_matcher_contract.wasm").to_vec())
+        .await?;
+    contract.call(&worker, "new").max_gas().transact().await?;
+    let parent_account = worker.dev_create_account().await?;
+
+    let starting_balance_for_each_acct = "10 Ⓝ".to_string();
+    let matcher_offer = "0.3 Ⓝ".to_string();
+    let recipient = create_subaccount(
+        &worker,
+        &parent_account,
+        "recipient",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+    let matcher = create_subaccount(
+        &worker,
+        &parent_account,
+        "matcher",
+        starting_balance_for_each_acct.as_str(),
+    )
+    .await?;
+
+    matcher
+        .call(&worker, contract.id(), "offer_matching_funds")
+        .args_json(json!({"recipient": &recipient.id()}))?
+        .max_gas()
+        .deposit(near_string_to_yocto(&matcher_offer))
+        .transact()
+        .await?;
+    matcher
+        .call(&worker, contract.id(), "rescind_matching_funds")
+        .args_json(
+            json!({"recipient": &recipient.id(), "requested_withdrawal_amount": matcher_offer}),
+        )?
+        .max_gas()
+        .transact()
+        .await?;
+    assert_expected_commitments(&contract, &worker, &recipient, json!({})).await?;
+
+    // Called directly (without a transfer before it), the callback takes its failed-transfer path. The whole commitment was rescinded, so that path has to recreate everything that goes with it, using no more than its static gas:
+    let callback_args = format!(
+        "{{\"recipient\":\"{}\",\"matcher\":\"{}\",\"rescinded_amount\":{}}}",
+        recipient.id(),
+        matcher.id(),
+        near_string_to_yocto(&matcher_offer)
+    ); // `json!` can't hold a u128.
+    let callback_result = contract
+        .call(&worker, "on_rescind_matching_funds")
+        .args(callback_args.into_bytes())
+        .gas(GAS_FOR_ON_RESCIND_MATCHING_FUNDS.0)
+        .transact()
+        .await?;
+    assert!(callback_result.is_success());
+    assert!(!callback_result.json::<bool>()?);
+    assert_expected_commitments(
+        &contract,
+        &worker,
+        &recipient,
+        json!({ matcher.id().to_string(): matcher_offer }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_donation_to_a_full_recipient() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let contract = worker
+        .dev_deploy(&include_bytes!("../target/res/donation_matcher_contract.wasm").to_vec())
+        .await?;
+    let token = worker
+        .dev_deploy(
+            &include_bytes!("mock-token/target/wasm32-unknown-unknown/release/mock_token.wasm")
+                .to_vec(),
+        )
+        .await?; // See tests/mock-token/src/lib.rs for how to build it.
+    contract.call(&worker, "new").max_gas().transact().await?;
+    token.call(&worker, "new").max_gas().transact().await?;
+    let parent_account = worker.dev_create_account().await?;
+
+    let matcher_offer = "0.3 Ⓝ".to_string();
+    let donation = "1 Ⓝ".to_string();
+    let recipient = create_subaccount(&worker, &parent_account, "recipient", "1 Ⓝ").await?;
+    let donor = create_subaccount(&worker, &parent_account, "donor", "5 Ⓝ").await?;
+    token
+        .call(&worker, "set_balance")
+        .args_json(json!({"account_id": &donor.id(), "balance": "1"}))?
+        .max_gas()
+        .transact()
+        .await?;
+
+    // Fill the recipient up to both caps, so that this donation is as expensive as any can be:
+    for index in 0..MAX_MATCHERS_PER_RECIPIENT {
+        let matcher = create_subaccount(
+            &worker,
+            &parent_account,
+            &format!("matcher{}", index),
+            "1 Ⓝ",
+        )
+        .await?;
+        if index < MAX_ELIGIBILITY_CHECKS_PER_RECIPIENT {
+            matcher
+                .call(&worker, contract.id(), "set_eligibility_check")
+                .args_json(json!({
+                    "recipient": &recipient.id(),
+                    "contract_id": &token.id(),
+                    "method": "ft_balance_of",
+                    "min_balance": "1"
+                }))?
+                .max_gas()
+                .deposit(near_string_to_yocto(&"0.01".to_string()))
+                .transact()
+                .await?;
+        }
+        matcher
+            .call(&worker, contract.id(), "offer_matching_funds")
+            .args_json(json!({"recipient": &recipient.id()}))?
+            .max_gas()
+            .deposit(near_string_to_yocto(&matcher_offer))
+            .transact()
+            .await?;
+    }
+
+    let estimated_gas: near_sdk::Gas = contract
+        .view(
+            &worker,
+            "estimate_donate_gas",
+            json!({"recipient": &recipient.id(), "donor": &donor.id()})
+                .to_string()
+                .into_bytes(),
+        )
+        .await?
+        .json()?;
+    let donate_result = donor
+        .call(&worker, contract.id(), "donate")
+        .args_json(json!({"recipient": &recipient.id()}))?
+        .gas(estimated_gas.0)
+        .deposit(near_string_to_yocto(&donation))
+        .transact()
+        .await?;
+    assert!(donate_result.is_success());
+    assert!(donate_result.total_gas_burnt <= estimated_gas.0);
+    assert_approx_considering_gas(
+        &recipient.view_account(&worker).await?.balance,
+        &(near_string_to_yocto(&"1 Ⓝ".to_string())
+            + near_string_to_yocto(&donation)
+            + MAX_MATCHERS_PER_RECIPIENT as u128 * near_string_to_yocto(&matcher_offer)),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+// A stand-in for an NFT or fungible token contract, so that the integration tests can exercise eligibility checks (see src/eligibility_checks.rs) against a real cross-contract call.
+// Build it with `cargo build --manifest-path tests/mock-token/Cargo.toml --target wasm32-unknown-unknown --release` before running the integration tests.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::{near_bindgen, AccountId, PanicOnDefault};
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockToken {
+    balances: LookupMap<AccountId, u128>, // Serves as both the number of NFTs and the fungible token balance of each account.
+}
+
+#[near_bindgen]
+impl MockToken {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            balances: LookupMap::new(b"b"),
+        }
+    }
+
+    pub fn set_balance(&mut self, account_id: AccountId, balance: U128) {
+        self.balances.insert(&account_id, &balance.0);
+    }
+
+    /// NEP-181 (NFT enumeration)
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.balances.get(&account_id).unwrap_or(0))
+    }
+
+    /// NEP-141 (fungible tokens)
+    pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        U128(self.balances.get(&account_id).unwrap_or(0))
+    }
+}